@@ -0,0 +1,91 @@
+// CPU throughput benchmark.
+//
+// Runs nestest's automated mode from $C000 on a flat 64KB memory, with no
+// PPU, APU or DMA behind the bus, so the time is spent in decoding and
+// executing instructions. Reports emulated instructions per second (best of
+// several rounds).
+//
+//   $ cargo bench --bench cpu
+//   $ cargo bench --bench cpu -- 2000
+extern crate rustynes;
+
+use std::env;
+use std::fs;
+use std::time::Instant;
+
+use rustynes::nes::{Cpu, CpuBus, CpuRegisters, Registers, Variant};
+
+const ROM: &str = "roms/nestest.nes";
+// Instructions in roms/nestest.log, the end of the automated tests.
+const INSTRUCTIONS: usize = 8991;
+const DEFAULT_PASSES: usize = 1000;
+const ROUNDS: usize = 5;
+
+// The core hands finished frames to the frontend through this hook.
+#[no_mangle]
+pub extern "C" fn canvas_render(_ptr: *const u8, _len: usize) {}
+
+struct FlatBus {
+    memory: Vec<u8>,
+}
+
+impl CpuBus for FlatBus {
+    fn read_word(&mut self, addr: u16) -> u16 {
+        let lower = self.read(addr) as u16;
+        let upper = self.read(addr.wrapping_add(1)) as u16;
+        lower | upper << 8
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        self.memory[addr as usize]
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        self.memory[addr as usize] = data;
+    }
+}
+
+// The 16KB program is mirrored at $8000 and $C000.
+fn load(path: &str) -> Vec<u8> {
+    let rom = fs::read(path).unwrap_or_else(|e| panic!("cannot open {}: {}", path, e));
+    let program = &rom[0x10..0x10 + 0x4000];
+    let mut memory = vec![0; 0x10000];
+    memory[0x8000..0xC000].copy_from_slice(program);
+    memory[0xC000..].copy_from_slice(program);
+    memory
+}
+
+fn main() {
+    // `cargo bench` passes `--bench` to harness-less targets.
+    let passes = env::args()
+        .skip(1)
+        .find(|a| !a.starts_with("--"))
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(DEFAULT_PASSES);
+    let image = load(ROM);
+    let cpu = Cpu::new(Variant::Ricoh2A03);
+    let mut registers = Registers::new();
+    let mut bus = FlatBus {
+        memory: image.clone(),
+    };
+    let mut elapsed = f64::MAX;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        for _ in 0..passes {
+            bus.memory.copy_from_slice(&image);
+            registers.reset(0xC000);
+            for _ in 0..INSTRUCTIONS {
+                cpu.step(&mut registers, &mut bus);
+            }
+        }
+        elapsed = elapsed.min(start.elapsed().as_secs_f64());
+    }
+    let instructions = passes * INSTRUCTIONS;
+    println!(
+        "{:<24} {:>9} instructions in {:>7.3}s  {:>7.2} M instructions/s",
+        ROM,
+        instructions,
+        elapsed,
+        instructions as f64 / elapsed / 1e6
+    );
+}
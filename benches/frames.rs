@@ -0,0 +1,66 @@
+// Emulation throughput benchmark.
+//
+// Runs each ROM headless for a fixed number of frames and reports how many
+// emulated frames per second the core manages (best of several rounds, to
+// keep scheduler noise out of the number).
+//
+//   $ cargo bench --bench frames
+//   $ cargo bench --bench frames -- roms/giko017.nes 3000
+extern crate rustynes;
+
+use std::env;
+use std::fs;
+use std::time::Instant;
+
+use rustynes::nes;
+use rustynes::nes::Context;
+
+const DEFAULT_ROMS: &[&str] = &["roms/falling.nes", "roms/giko017.nes", "roms/nestest.nes"];
+const DEFAULT_FRAMES: usize = 1200;
+const WARMUP_FRAMES: usize = 60;
+const ROUNDS: usize = 5;
+
+// The core hands finished frames to the frontend through this hook.
+#[no_mangle]
+pub extern "C" fn canvas_render(_ptr: *const u8, _len: usize) {}
+
+fn bench(path: &str, frames: usize) {
+    let mut rom = fs::read(path).unwrap_or_else(|e| panic!("cannot open {}: {}", path, e));
+    let mut ctx = Context::new(&mut rom);
+    nes::reset(&mut ctx);
+    for _ in 0..WARMUP_FRAMES {
+        nes::run(&mut ctx, 0, 0);
+    }
+    let mut elapsed = f64::MAX;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        for _ in 0..frames {
+            nes::run(&mut ctx, 0, 0);
+        }
+        elapsed = elapsed.min(start.elapsed().as_secs_f64());
+    }
+    println!(
+        "{:<24} {:>6} frames in {:>7.3}s  {:>9.1} frames/s",
+        path,
+        frames,
+        elapsed,
+        frames as f64 / elapsed
+    );
+}
+
+fn main() {
+    // `cargo bench` passes `--bench` to harness-less targets.
+    let args: Vec<String> = env::args().skip(1).filter(|a| !a.starts_with("--")).collect();
+    let frames = args
+        .get(1)
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(DEFAULT_FRAMES);
+    match args.first() {
+        Some(path) => bench(path, frames),
+        None => {
+            for path in DEFAULT_ROMS {
+                bench(path, frames);
+            }
+        }
+    }
+}
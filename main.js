@@ -3,6 +3,9 @@ import Noise from './src/nes/browser/noise.js';
 
 let buf = null
 
+// Lines and columns hidden by a TV, cropped from the 256x240 picture.
+const DEFAULT_OVERSCAN = { top: 8, bottom: 8, left: 0, right: 0 };
+
 const convertKeyCode = (keyCode) => {
   switch (keyCode) {
   case 88: return 0x01; // X  A
@@ -35,7 +38,7 @@ const setupKeyHandler = () => {
 
 setupKeyHandler();
 
-const startArrayBuf = (arrayBuf) => {
+const startArrayBuf = (arrayBuf, overscan = DEFAULT_OVERSCAN) => {
   const run = Module.cwrap('run', null, ['number', 'number']);
   const canvas = document.querySelector("canvas");
   const ctx = canvas.getContext('2d');
@@ -49,9 +52,10 @@ const startArrayBuf = (arrayBuf) => {
     image: ctx.createImageData(256, 240),
     oscs: [new Oscillator(), new Oscillator(), new Oscillator('triangle')],
     noise: new Noise(),
+    overscan,
   }
-  canvas.width = 256;
-  canvas.height = 240;
+  canvas.width = 256 - overscan.left - overscan.right;
+  canvas.height = 240 - overscan.top - overscan.bottom;
 
   const nes = new Uint8Array(arrayBuf);
   // Add key code area to tail.
@@ -64,13 +68,13 @@ const startArrayBuf = (arrayBuf) => {
   run(size, buf.byteOffset);
 };
 
-export const start = async (rom = './roms/falling.nes') => {
+export const start = async (rom = './roms/falling.nes', overscan) => {
   const res = await fetch(rom);
   const arrayBuf = await res.arrayBuffer();
-  startArrayBuf(arrayBuf);
+  startArrayBuf(arrayBuf, overscan);
 };
 
-export const startFile = async (file) => {
+export const startFile = async (file, overscan) => {
   const loadFile = (file) => {
     return new Promise((resolve, reject) => {
       const reader = new FileReader();
@@ -81,5 +85,5 @@ export const startFile = async (file) => {
   };
 
   const arrayBuf = await loadFile(file)
-  startArrayBuf(arrayBuf);
+  startArrayBuf(arrayBuf, overscan);
 };
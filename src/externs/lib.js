@@ -2,7 +2,8 @@ mergeInto(LibraryManager.library, {
   canvas_render: function (ptr, len) {
     Module.NES.buf = new Uint8Array(Module.HEAPU8.buffer, ptr, len);
     Module.NES.image.data.set(Module.NES.buf);
-    Module.NES.ctx.putImageData(Module.NES.image, 0, 0);
+    const { top, bottom, left, right } = Module.NES.overscan;
+    Module.NES.ctx.putImageData(Module.NES.image, -left, -top, left, top, 256 - left - right, 240 - top - bottom);
   },
   start_oscillator: function (index) {
     Module.NES.oscs[index].start();
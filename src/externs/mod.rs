@@ -31,7 +31,8 @@ pub fn cancel_main_loop() {
     }
 }
 
-pub fn set_main_loop_callback<F>(callback: F)
+// `fps` 0 follows the display refresh.
+pub fn set_main_loop_callback<F>(callback: F, fps: i32)
 where
     F: FnMut(),
 {
@@ -40,7 +41,7 @@ where
     });
 
     unsafe {
-        emscripten_set_main_loop(wrapper::<F>, 0, 1);
+        emscripten_set_main_loop(wrapper::<F>, fps, 1);
     }
 }
 
@@ -1,4 +1 @@
-#[macro_use]
-extern crate lazy_static;
-
 pub mod nes;
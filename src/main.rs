@@ -1,11 +1,9 @@
-#[macro_use]
-extern crate lazy_static;
 extern crate libc;
 
 mod externs;
 mod nes;
 
-use nes::Context;
+use nes::{Context, Region};
 
 fn main() {}
 
@@ -14,12 +12,18 @@ pub fn run(len: usize, ptr: *mut u8) {
     let buf: &mut [u8] = unsafe { std::slice::from_raw_parts_mut(ptr, len + 1) };
     let mut ctx = Context::new(buf);
     nes::reset(&mut ctx);
+    // Displays refresh at about 60Hz, which is close enough for NTSC.
+    let fps = match ctx.get_region() {
+        Region::Ntsc => 0,
+        region => region.frame_rate().round() as i32,
+    };
     externs::cancel_main_loop();
     let main_loop = || {
         let key_state = buf[len - 1];
         // externs::eval("console.time('nes.run')");
-        nes::run(&mut ctx, key_state);
+        // The page only has keys for the first controller.
+        nes::run(&mut ctx, key_state, 0);
         // externs::eval("console.timeEnd('nes.run')");
     };
-    externs::set_main_loop_callback(main_loop);
+    externs::set_main_loop_callback(main_loop, fps);
 }
@@ -1,24 +1,39 @@
-pub const CPU_CLOCK: usize = 1789772;
-
-pub const GROBAL_GAIN: f32 = 0.1;
+// Rate of the samples handed to the frontend, unless it asks for another.
+pub const SAMPLE_RATE: usize = 44100;
 
 pub const COUNTER_TABLE: &'static [u8] = &[
     0x0A, 0xFE, 0x14, 0x02, 0x28, 0x04, 0x50, 0x06, 0xA0, 0x08, 0x3C, 0x0A, 0x0E, 0x0C, 0x1A, 0x0E,
     0x0C, 0x10, 0x18, 0x12, 0x30, 0x14, 0x60, 0x16, 0xC0, 0x18, 0x48, 0x1A, 0x10, 0x1C, 0x20, 0x1E,
 ];
 
-pub const DIVIDE_COUNT_FOR_240HZ: u16 = 7457;
+// CPU cycles of the frame counter steps in the 4-step and 5-step modes.
+// The last one is cycle 0 of the next sequence.
+pub const FRAME_COUNTER_STEPS: [[usize; 6]; 2] = [
+    [7457, 14913, 22371, 29828, 29829, 29830],
+    [7457, 14913, 22371, 29829, 37281, 37282],
+];
+
+pub const PAL_FRAME_COUNTER_STEPS: [[usize; 6]; 2] = [
+    [8313, 16627, 24939, 33252, 33253, 33254],
+    [8313, 16627, 24939, 33253, 41565, 41566],
+];
 
 pub const NOISE_TIMER_PERIOD_TABLE: &'static [u16] = &[
     0x004, 0x008, 0x010, 0x020, 0x040, 0x060, 0x080, 0x0A0, 0x0CA, 0x0FE, 0x17C, 0x1FC, 0x2FA,
     0x3F8, 0x7F2, 0xFE4,
 ];
 
-/*
-export const dmcTimerPeriodTable = [
-  0x1AC, 0x17C, 0x154, 0x140,
-  0x11E, 0x0FE, 0x0E2, 0x0D6,
-  0x0BE, 0x0A0, 0x08E, 0x080,
-  0x06A, 0x054, 0x048, 0x036,
+pub const DMC_TIMER_PERIOD_TABLE: &'static [u16] = &[
+    0x1AC, 0x17C, 0x154, 0x140, 0x11E, 0x0FE, 0x0E2, 0x0D6, 0x0BE, 0x0A0, 0x08E, 0x080, 0x06A,
+    0x054, 0x048, 0x036,
+];
+
+pub const PAL_NOISE_TIMER_PERIOD_TABLE: &[u16] = &[
+    0x004, 0x008, 0x00E, 0x01E, 0x03C, 0x058, 0x076, 0x094, 0x0BC, 0x0EC, 0x162, 0x1D8, 0x2C4,
+    0x3B0, 0x762, 0xEC2,
+];
+
+pub const PAL_DMC_TIMER_PERIOD_TABLE: &[u16] = &[
+    0x18E, 0x162, 0x13C, 0x12A, 0x114, 0x0EC, 0x0D2, 0x0C6, 0x0B0, 0x094, 0x084, 0x076, 0x062,
+    0x04E, 0x042, 0x032,
 ];
-*/
@@ -0,0 +1,226 @@
+use super::ChannelState;
+use nes::types::{Addr, Data};
+
+// Delta modulation channel.
+// Plays 1 bit deltas read from $C000-$FFFF: each bit moves a 7 bit output level
+// up or down by 2. Every sample byte is fetched through DMA, which stalls the CPU.
+#[derive(Debug)]
+pub struct Dmc {
+    is_irq_enabled: bool,
+    is_loop: bool,
+    timer_period_table: &'static [u16],
+    timer_period: u16,
+    timer: u16,
+
+    sample_addr: Addr,
+    sample_length: u16,
+    current_addr: Addr,
+    bytes_remaining: u16,
+
+    sample_buffer: Option<Data>,
+    shift_register: Data,
+    bits_remaining: u8,
+    silence: bool,
+    output_level: Data,
+    // Set when a sample ends without looping, cleared by $4015 writes.
+    irq: bool,
+}
+
+impl Dmc {
+    pub fn new(timer_period_table: &'static [u16]) -> Self {
+        Dmc {
+            is_irq_enabled: false,
+            is_loop: false,
+            timer_period_table,
+            timer_period: timer_period_table[0],
+            timer: timer_period_table[0],
+            sample_addr: 0xC000,
+            sample_length: 1,
+            current_addr: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            output_level: 0,
+            irq: false,
+        }
+    }
+
+    pub fn write(&mut self, addr: Addr, data: Data) {
+        match addr {
+            0x00 => {
+                self.is_irq_enabled = data & 0x80 == 0x80;
+                if !self.is_irq_enabled {
+                    self.irq = false;
+                }
+                self.is_loop = data & 0x40 == 0x40;
+                self.timer_period = self.timer_period_table[(data & 0x0F) as usize];
+            }
+            // Direct load of the output level.
+            0x01 => self.output_level = data & 0x7F,
+            0x02 => self.sample_addr = 0xC000 | ((data as Addr) << 6),
+            0x03 => self.sample_length = ((data as u16) << 4) + 1,
+            _ => (),
+        }
+    }
+
+    pub fn enable(&mut self) {
+        if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    pub fn disable(&mut self) {
+        self.bytes_remaining = 0;
+    }
+
+    pub fn has_count_end(&self) -> bool {
+        self.bytes_remaining == 0
+    }
+
+    // The output level doubles as the volume.
+    pub fn state(&self) -> ChannelState {
+        ChannelState {
+            period: self.timer_period,
+            frequency: 0.0,
+            volume: self.output_level,
+            duty: 0,
+            length: self.bytes_remaining,
+            output: self.output_level,
+        }
+    }
+
+    pub fn is_irq(&self) -> bool {
+        self.irq
+    }
+
+    pub fn clear_irq(&mut self) {
+        self.irq = false;
+    }
+
+    // Current level, 0 to 127.
+    pub fn output(&self) -> Data {
+        self.output_level
+    }
+
+    // The timer counts CPU cycles.
+    pub fn run(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.timer_period;
+        if !self.silence {
+            // The level stays put instead of wrapping around.
+            if self.shift_register & 0x01 == 0x01 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+            self.shift_register >>= 1;
+        }
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(data) => {
+                    self.shift_register = data;
+                    self.silence = false;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    // The reader fetches the next sample byte as soon as the buffer is emptied.
+    pub fn dma_request(&self) -> Option<Addr> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_addr)
+        } else {
+            None
+        }
+    }
+
+    pub fn dma_complete(&mut self, data: Data) {
+        self.sample_buffer = Some(data);
+        self.current_addr = if self.current_addr == 0xFFFF {
+            0x8000
+        } else {
+            self.current_addr + 1
+        };
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.is_loop {
+                self.restart();
+            } else if self.is_irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_addr = self.sample_addr;
+        self.bytes_remaining = self.sample_length;
+    }
+}
+
+#[cfg(test)]
+use super::constants::DMC_TIMER_PERIOD_TABLE;
+
+#[test]
+fn sample_address_and_length() {
+    let mut dmc = Dmc::new(DMC_TIMER_PERIOD_TABLE);
+    dmc.write(0x02, 0x01);
+    dmc.write(0x03, 0x01);
+    dmc.enable();
+    assert_eq!(dmc.dma_request(), Some(0xC040));
+    for _ in 0..17 {
+        assert!(dmc.dma_request().is_some());
+        dmc.dma_complete(0);
+        dmc.sample_buffer = None;
+    }
+    assert_eq!(dmc.dma_request(), None);
+}
+
+#[test]
+fn reader_waits_for_the_buffer_to_empty() {
+    let mut dmc = Dmc::new(DMC_TIMER_PERIOD_TABLE);
+    dmc.write(0x00, 0x0F);
+    dmc.write(0x03, 0x01);
+    dmc.enable();
+    dmc.dma_complete(0xAA);
+    assert_eq!(dmc.dma_request(), None);
+    // The running output cycle still has 8 bits to go, and the timer
+    // only picks up the new rate when it reloads.
+    for _ in 0..428 + 54 * 7 {
+        dmc.run();
+    }
+    assert_eq!(dmc.dma_request(), Some(0xC001));
+}
+
+#[test]
+fn output_level_and_irq() {
+    let mut dmc = Dmc::new(DMC_TIMER_PERIOD_TABLE);
+    dmc.write(0x00, 0x8F);
+    dmc.write(0x01, 0x7E);
+    dmc.write(0x03, 0x00);
+    dmc.enable();
+    dmc.dma_complete(0x0F);
+    // The only byte was fetched, the sample ends with an IRQ.
+    assert!(dmc.is_irq());
+    // Play the silent cycle, then 4 ups and 4 downs.
+    let mut levels = vec![];
+    for _ in 0..428 + 54 * 16 {
+        dmc.run();
+        if levels.last() != Some(&dmc.output()) {
+            levels.push(dmc.output());
+        }
+    }
+    // Stays at 126 as 128 does not fit, then goes down.
+    assert_eq!(levels, vec![0x7E, 0x7C, 0x7A, 0x78, 0x76]);
+    dmc.write(0x00, 0x0F);
+    assert!(!dmc.is_irq());
+}
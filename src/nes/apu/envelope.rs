@@ -0,0 +1,121 @@
+use nes::types::Data;
+
+// Volume of the pulse and noise channels ($4000, $4004, $400C).
+// Either a constant volume, or a decay from 15 down to 0 that optionally
+// loops. The loop flag is the same bit as the length counter halt.
+#[derive(Debug)]
+pub struct Envelope {
+    is_loop: bool,
+    is_constant_volume: bool,
+    // Constant volume, or the divider period of the decay.
+    period: u8,
+    // Set by a write to the 4th register, the next clock restarts the decay.
+    is_start: bool,
+    divider: u8,
+    decay_level: u8,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Envelope {
+            is_loop: false,
+            is_constant_volume: false,
+            period: 0,
+            is_start: false,
+            divider: 0,
+            decay_level: 0,
+        }
+    }
+
+    pub fn write(&mut self, data: Data) {
+        self.is_loop = data & 0x20 == 0x20;
+        self.is_constant_volume = data & 0x10 == 0x10;
+        self.period = data & 0x0F;
+    }
+
+    pub fn restart(&mut self) {
+        self.is_start = true;
+    }
+
+    // Clocked by the frame counter every quarter frame.
+    pub fn clock(&mut self) {
+        if self.is_start {
+            self.is_start = false;
+            self.decay_level = 15;
+            self.divider = self.period;
+            return;
+        }
+        if self.divider > 0 {
+            self.divider -= 1;
+            return;
+        }
+        self.divider = self.period;
+        if self.decay_level > 0 {
+            self.decay_level -= 1;
+        } else if self.is_loop {
+            self.decay_level = 15;
+        }
+    }
+
+    // 0 to 15.
+    pub fn volume(&self) -> u8 {
+        if self.is_constant_volume {
+            self.period
+        } else {
+            self.decay_level
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decay() {
+        let mut envelope = Envelope::new();
+        // Period 1, so every other clock.
+        envelope.write(0x01);
+        envelope.restart();
+        envelope.clock();
+        assert_eq!(envelope.volume(), 15);
+        envelope.clock();
+        assert_eq!(envelope.volume(), 15);
+        envelope.clock();
+        assert_eq!(envelope.volume(), 14);
+        for _ in 0..28 {
+            envelope.clock();
+        }
+        assert_eq!(envelope.volume(), 0);
+        // Stays at 0 without the loop flag.
+        for _ in 0..4 {
+            envelope.clock();
+        }
+        assert_eq!(envelope.volume(), 0);
+    }
+
+    #[test]
+    fn decay_loop() {
+        let mut envelope = Envelope::new();
+        envelope.write(0x20);
+        envelope.restart();
+        let levels: Vec<u8> = (0..18)
+            .map(|_| {
+                envelope.clock();
+                envelope.volume()
+            })
+            .collect();
+        assert_eq!(&levels[14..], &[1, 0, 15, 14]);
+    }
+
+    #[test]
+    fn constant_volume() {
+        let mut envelope = Envelope::new();
+        envelope.write(0x17);
+        envelope.restart();
+        for _ in 0..20 {
+            envelope.clock();
+            assert_eq!(envelope.volume(), 7);
+        }
+    }
+}
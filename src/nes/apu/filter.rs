@@ -0,0 +1,91 @@
+// First order RC filters on the way from the APU to the audio jack.
+// On a front loader the output goes through two high-pass filters, which
+// remove the DC offset, and a low-pass that takes the edge off the pulses.
+#[derive(Debug)]
+enum Kind {
+    HighPass,
+    LowPass,
+}
+
+#[derive(Debug)]
+pub struct Filter {
+    kind: Kind,
+    alpha: f32,
+    prev_input: f32,
+    prev_output: f32,
+}
+
+impl Filter {
+    pub fn high_pass(cutoff: f32, sample_rate: f32) -> Self {
+        let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        Filter {
+            kind: Kind::HighPass,
+            alpha: rc / (rc + dt),
+            prev_input: 0.0,
+            prev_output: 0.0,
+        }
+    }
+
+    pub fn low_pass(cutoff: f32, sample_rate: f32) -> Self {
+        let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        Filter {
+            kind: Kind::LowPass,
+            alpha: dt / (rc + dt),
+            prev_input: 0.0,
+            prev_output: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = match self.kind {
+            Kind::HighPass => self.alpha * (self.prev_output + input - self.prev_input),
+            Kind::LowPass => self.prev_output + self.alpha * (input - self.prev_output),
+        };
+        self.prev_input = input;
+        self.prev_output = output;
+        output
+    }
+}
+
+// The NES filter chain: high-pass at 90Hz and 440Hz, low-pass at 14kHz.
+pub fn output_filters(sample_rate: usize) -> Vec<Filter> {
+    let rate = sample_rate as f32;
+    vec![
+        Filter::high_pass(90.0, rate),
+        Filter::high_pass(440.0, rate),
+        Filter::low_pass(14_000.0, rate),
+    ]
+}
+
+#[test]
+fn test_high_pass_removes_dc() {
+    let mut filter = Filter::high_pass(90.0, 44100.0);
+    let mut output = 0.0;
+    for _ in 0..44100 / 10 {
+        output = filter.process(0.5);
+    }
+    assert!(output.abs() < 0.001, "{}", output);
+}
+
+#[test]
+fn test_low_pass_attenuates_high_frequencies() {
+    let mut filter = Filter::low_pass(14_000.0, 44100.0);
+    // Alternating samples are the Nyquist frequency, 22.05kHz.
+    let mut peak: f32 = 0.0;
+    for i in 0..1000 {
+        let output = filter.process(if i % 2 == 0 { 1.0 } else { -1.0 });
+        if i > 100 {
+            peak = peak.max(output.abs());
+        }
+    }
+    assert!(peak < 0.5, "{}", peak);
+    // A slow signal goes through.
+    let mut filter = Filter::low_pass(14_000.0, 44100.0);
+    let mut output = 0.0;
+    for _ in 0..100 {
+        output = filter.process(1.0);
+    }
+    assert!((output - 1.0).abs() < 0.001);
+}
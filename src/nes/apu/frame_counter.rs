@@ -0,0 +1,196 @@
+use nes::types::Data;
+
+// What the frame counter clocks on a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Clock {
+    // Envelopes and the triangle linear counter.
+    Quarter,
+    // Length counters and sweeps as well.
+    Half,
+}
+
+// Divides the CPU clock into quarter and half frames ($4017).
+// The steps are the CPU cycles since the last $4017 write took effect, the
+// last one starts the sequence over. In the 4-step mode the IRQ flag is set
+// on the 3 cycles around the end of the sequence unless inhibited.
+#[derive(Debug)]
+pub struct FrameCounter {
+    steps: [[usize; 6]; 2],
+    cycle: usize,
+    step: usize,
+    is_five_step_mode: bool,
+    is_irq_inhibited: bool,
+    irq: bool,
+    // $4017 writes take effect after a few cycles.
+    pending_write: Option<Data>,
+    write_delay: usize,
+}
+
+impl FrameCounter {
+    pub fn new(steps: [[usize; 6]; 2]) -> Self {
+        FrameCounter {
+            steps,
+            cycle: 0,
+            step: 0,
+            is_five_step_mode: false,
+            is_irq_inhibited: false,
+            irq: false,
+            pending_write: None,
+            write_delay: 0,
+        }
+    }
+
+    // Runs a CPU cycle.
+    pub fn run(&mut self) -> Option<Clock> {
+        if let Some(data) = self.pending_write {
+            self.write_delay -= 1;
+            if self.write_delay == 0 {
+                self.pending_write = None;
+                self.is_five_step_mode = data & 0x80 == 0x80;
+                self.cycle = 0;
+                self.step = 0;
+                // Entering the 5-step mode clocks everything right away.
+                return if self.is_five_step_mode {
+                    Some(Clock::Half)
+                } else {
+                    None
+                };
+            }
+        }
+        self.cycle += 1;
+        if self.cycle != self.steps[self.is_five_step_mode as usize][self.step] {
+            return None;
+        }
+        if !self.is_five_step_mode && self.step >= 3 && !self.is_irq_inhibited {
+            self.irq = true;
+        }
+        let clock = match self.step {
+            0 | 2 => Some(Clock::Quarter),
+            1 | 4 => Some(Clock::Half),
+            _ => None,
+        };
+        self.step += 1;
+        if self.step == 6 {
+            self.step = 0;
+            self.cycle = 0;
+        }
+        clock
+    }
+
+    // The write lands 3 cycles later when it happens on an APU cycle,
+    // or 4 when it happens between 2.
+    pub fn write(&mut self, data: Data, is_odd_cycle: bool) {
+        self.is_irq_inhibited = data & 0x40 == 0x40;
+        if self.is_irq_inhibited {
+            self.irq = false;
+        }
+        self.pending_write = Some(data);
+        self.write_delay = if is_odd_cycle { 4 } else { 3 };
+    }
+
+    // The reset button restarts the sequence in the last written mode.
+    pub fn reset(&mut self) {
+        self.cycle = 0;
+        self.step = 0;
+        self.irq = false;
+        self.pending_write = None;
+    }
+
+    pub fn is_irq(&self) -> bool {
+        self.irq
+    }
+
+    pub fn clear_irq(&mut self) {
+        self.irq = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nes::apu::constants::FRAME_COUNTER_STEPS;
+
+    // Cycles since the write took effect for every clock in `cycles` cycles.
+    fn clocks(counter: &mut FrameCounter, cycles: usize) -> Vec<(usize, Clock)> {
+        (1..=cycles)
+            .filter_map(|cycle| counter.run().map(|clock| (cycle, clock)))
+            .collect()
+    }
+
+    #[test]
+    fn test_four_step_mode() {
+        let mut counter = FrameCounter::new(FRAME_COUNTER_STEPS);
+        assert_eq!(
+            clocks(&mut counter, 29830 * 2),
+            vec![
+                (7457, Clock::Quarter),
+                (14913, Clock::Half),
+                (22371, Clock::Quarter),
+                (29829, Clock::Half),
+                (29830 + 7457, Clock::Quarter),
+                (29830 + 14913, Clock::Half),
+                (29830 + 22371, Clock::Quarter),
+                (29830 + 29829, Clock::Half),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_irq() {
+        let mut counter = FrameCounter::new(FRAME_COUNTER_STEPS);
+        clocks(&mut counter, 29827);
+        assert!(!counter.is_irq());
+        clocks(&mut counter, 1);
+        assert!(counter.is_irq());
+        // Clearing it on the next 2 cycles does not stick.
+        counter.clear_irq();
+        clocks(&mut counter, 1);
+        assert!(counter.is_irq());
+        counter.clear_irq();
+        clocks(&mut counter, 1);
+        assert!(counter.is_irq());
+        counter.clear_irq();
+        clocks(&mut counter, 1);
+        assert!(!counter.is_irq());
+        // Inhibiting clears the flag and keeps it clear.
+        clocks(&mut counter, 29828);
+        assert!(counter.is_irq());
+        counter.write(0x40, false);
+        assert!(!counter.is_irq());
+        clocks(&mut counter, 29830);
+        assert!(!counter.is_irq());
+    }
+
+    #[test]
+    fn test_five_step_mode() {
+        let mut counter = FrameCounter::new(FRAME_COUNTER_STEPS);
+        counter.write(0x80, false);
+        let clocks = clocks(&mut counter, 3 + 37282);
+        assert_eq!(
+            clocks,
+            vec![
+                // Right when the write lands.
+                (3, Clock::Half),
+                (3 + 7457, Clock::Quarter),
+                (3 + 14913, Clock::Half),
+                (3 + 22371, Clock::Quarter),
+                (3 + 37281, Clock::Half),
+            ]
+        );
+        assert!(!counter.is_irq());
+    }
+
+    #[test]
+    fn test_write_delay() {
+        let mut counter = FrameCounter::new(FRAME_COUNTER_STEPS);
+        counter.write(0x80, true);
+        assert_eq!(clocks(&mut counter, 4), vec![(4, Clock::Half)]);
+        // Back to the 4-step mode, the sequence restarts after the delay.
+        clocks(&mut counter, 1000);
+        counter.write(0x00, false);
+        assert_eq!(
+            clocks(&mut counter, 3 + 7457),
+            vec![(3 + 7457, Clock::Quarter)]
+        );
+    }
+}
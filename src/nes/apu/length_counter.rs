@@ -0,0 +1,95 @@
+use super::constants::COUNTER_TABLE;
+use nes::types::Data;
+
+// Silences a channel after a number of half frames, loaded from a table by
+// writes to the 4th register. Only loaded while the channel is enabled in
+// $4015, and disabling it clears the count.
+#[derive(Debug)]
+pub struct LengthCounter {
+    is_enabled: bool,
+    // Same bit as the envelope loop, or the triangle linear counter control.
+    is_halted: bool,
+    count: u8,
+}
+
+impl LengthCounter {
+    pub fn new() -> Self {
+        LengthCounter {
+            is_enabled: false,
+            is_halted: false,
+            count: 0,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.is_enabled = enabled;
+        if !enabled {
+            self.count = 0;
+        }
+    }
+
+    pub fn set_halted(&mut self, halted: bool) {
+        self.is_halted = halted;
+    }
+
+    // Takes the data written to the 4th register, the index is in bits 3-7.
+    pub fn load(&mut self, data: Data) {
+        if self.is_enabled {
+            self.count = COUNTER_TABLE[data as usize >> 3];
+        }
+    }
+
+    // Clocked by the frame counter every half frame.
+    pub fn clock(&mut self) {
+        if !self.is_halted && self.count > 0 {
+            self.count -= 1;
+        }
+    }
+
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.count == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_and_clock() {
+        let mut counter = LengthCounter::new();
+        // Not loaded while disabled.
+        counter.load(0x00);
+        assert!(counter.is_zero());
+        counter.set_enabled(true);
+        counter.load(0x00);
+        assert_eq!(counter.count, 10);
+        counter.load(0x08);
+        assert_eq!(counter.count, 254);
+        counter.load(0x18);
+        counter.clock();
+        assert_eq!(counter.count, 1);
+        counter.clock();
+        counter.clock();
+        assert!(counter.is_zero());
+    }
+
+    #[test]
+    fn halt_and_disable() {
+        let mut counter = LengthCounter::new();
+        counter.set_enabled(true);
+        counter.load(0x00);
+        counter.set_halted(true);
+        counter.clock();
+        assert_eq!(counter.count, 10);
+        counter.set_halted(false);
+        counter.clock();
+        assert_eq!(counter.count, 9);
+        counter.set_enabled(false);
+        assert!(counter.is_zero());
+    }
+}
@@ -0,0 +1,152 @@
+// The channels are mixed by resistor networks, which do not add up linearly:
+// the louder one group of channels gets, the less another step adds.
+// Lookup tables from NESdev:
+//   pulse_table[n] = 95.52 / (8128.0 / n + 100)
+//   tnd_table[n] = 163.67 / (24329.0 / n + 100)
+// with n = pulse1 + pulse2 and n = 3 * triangle + 2 * noise + dmc.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+    // Cartridge audio goes through the expansion pin, with a level that
+    // depends on the console revision.
+    Expansion,
+}
+
+const CHANNELS: usize = 6;
+
+#[derive(Debug)]
+pub struct Mixer {
+    pulse_table: Vec<f32>,
+    tnd_table: Vec<f32>,
+    // Indexed by `Channel`, 1.0 is the console's level.
+    volumes: [f32; CHANNELS],
+    muted: [bool; CHANNELS],
+    // When any channel is soloed, only the soloed ones are heard.
+    soloed: [bool; CHANNELS],
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        let pulse_table = (0..31)
+            .map(|n| {
+                if n == 0 {
+                    0.0
+                } else {
+                    95.52 / (8128.0 / n as f32 + 100.0)
+                }
+            })
+            .collect();
+        let tnd_table = (0..203)
+            .map(|n| {
+                if n == 0 {
+                    0.0
+                } else {
+                    163.67 / (24329.0 / n as f32 + 100.0)
+                }
+            })
+            .collect();
+        Mixer {
+            pulse_table,
+            tnd_table,
+            volumes: [1.0; CHANNELS],
+            muted: [false; CHANNELS],
+            soloed: [false; CHANNELS],
+        }
+    }
+
+    pub fn set_volume(&mut self, channel: Channel, volume: f32) {
+        self.volumes[channel as usize] = volume.max(0.0);
+    }
+
+    pub fn get_volume(&self, channel: Channel) -> f32 {
+        self.volumes[channel as usize]
+    }
+
+    pub fn set_muted(&mut self, channel: Channel, muted: bool) {
+        self.muted[channel as usize] = muted;
+    }
+
+    pub fn set_solo(&mut self, channel: Channel, solo: bool) {
+        self.soloed[channel as usize] = solo;
+    }
+
+    // Volume after mute and solo.
+    fn gain(&self, channel: Channel) -> f32 {
+        let i = channel as usize;
+        let is_soloing = self.soloed.iter().any(|&solo| solo);
+        if self.muted[i] || (is_soloing && !self.soloed[i]) {
+            0.0
+        } else {
+            self.volumes[i]
+        }
+    }
+
+    // Levels are 0-15, except the DMC at 0-127. The result is 0.0 to about 1.0.
+    pub fn mix(&self, levels: [u8; 5], expansion: f32) -> f32 {
+        let level = |channel: Channel| levels[channel as usize] as f32 * self.gain(channel);
+        let pulse = level(Channel::Pulse1) + level(Channel::Pulse2);
+        let tnd =
+            3.0 * level(Channel::Triangle) + 2.0 * level(Channel::Noise) + level(Channel::Dmc);
+        lookup(&self.pulse_table, pulse)
+            + lookup(&self.tnd_table, tnd)
+            + expansion * self.gain(Channel::Expansion)
+    }
+}
+
+// Volumes make the index fractional, so neighbouring entries are interpolated.
+// Past the end the last step is extended.
+fn lookup(table: &[f32], index: f32) -> f32 {
+    let last = table.len() - 1;
+    let i = std::cmp::min(index as usize, last - 1);
+    let fraction = index - i as f32;
+    table[i] + (table[i + 1] - table[i]) * fraction
+}
+
+#[test]
+fn test_mix() {
+    let mut mixer = Mixer::new();
+    assert_eq!(mixer.mix([0; 5], 0.0), 0.0);
+    let full = mixer.mix([15, 15, 15, 15, 127], 0.0);
+    // 0.2575 from the pulses and 0.7425 from the others.
+    assert!((full - 1.0).abs() < 0.001, "{}", full);
+    // The second pulse adds less than the first.
+    let one = mixer.mix([15, 0, 0, 0, 0], 0.0);
+    let two = mixer.mix([15, 15, 0, 0, 0], 0.0);
+    assert!(two < one * 2.0);
+    // Half volume of 2 channels at 15 is 1 channel at 15.
+    mixer.set_volume(Channel::Pulse1, 0.5);
+    mixer.set_volume(Channel::Pulse2, 0.5);
+    assert_eq!(mixer.mix([15, 15, 0, 0, 0], 0.0), one);
+    // Fractional levels fall between the table entries.
+    let quarter = mixer.mix([15, 0, 0, 0, 0], 0.0);
+    assert!(quarter > mixer.pulse_table[7] && quarter < mixer.pulse_table[8]);
+    mixer.set_volume(Channel::Pulse1, 0.0);
+    mixer.set_volume(Channel::Pulse2, 0.0);
+    assert_eq!(mixer.mix([15, 15, 0, 0, 0], 0.0), 0.0);
+}
+
+#[test]
+fn test_mute_and_solo() {
+    let mut mixer = Mixer::new();
+    let levels = [15, 15, 15, 15, 127];
+    let all = mixer.mix(levels, 0.0);
+    let pulse1 = mixer.mix([15, 0, 0, 0, 0], 0.0);
+    let triangle = mixer.mix([0, 0, 15, 0, 0], 0.0);
+    mixer.set_solo(Channel::Pulse1, true);
+    assert_eq!(mixer.mix(levels, 1.0), pulse1);
+    mixer.set_solo(Channel::Triangle, true);
+    assert_eq!(mixer.mix(levels, 1.0), pulse1 + triangle);
+    // Muting wins over solo.
+    mixer.set_muted(Channel::Pulse1, true);
+    assert_eq!(mixer.mix(levels, 1.0), triangle);
+    mixer.set_solo(Channel::Pulse1, false);
+    mixer.set_solo(Channel::Triangle, false);
+    mixer.set_muted(Channel::Pulse1, false);
+    assert_eq!(mixer.mix(levels, 0.0), all);
+    mixer.set_solo(Channel::Expansion, true);
+    assert_eq!(mixer.mix(levels, 0.5), 0.5);
+}
@@ -1,55 +1,137 @@
 mod constants;
+mod dmc;
+mod envelope;
+mod filter;
+mod frame_counter;
+mod length_counter;
+mod mixer;
 mod noise;
+mod resampler;
 mod square;
+mod sweep;
 mod triangle;
 
 use self::constants::*;
+use self::dmc::Dmc;
+use self::filter::{output_filters, Filter};
+use self::frame_counter::{Clock, FrameCounter};
+pub use self::mixer::{Channel, Mixer};
 use self::noise::Noise;
+use self::resampler::Resampler;
 use self::square::Square;
 use self::triangle::Triangle;
+use std::collections::VecDeque;
+
+use nes::recorder::VgmLogger;
+use nes::region::Region;
 use nes::types::{Addr, Data};
 
+// What a channel is doing right now, for debuggers and visualizers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelState {
+    // Timer period: the 11 bit register value of the pulses and the
+    // triangle, CPU cycles per step for the noise and the DMC.
+    pub period: u16,
+    // Pitch in Hz, or the rate of the shift register for the noise and the DMC.
+    pub frequency: f32,
+    // 0-15, the DMC output level 0-127.
+    pub volume: u8,
+    // Pulse duty cycle 0-3 (12.5%, 25%, 50%, 25% negated), 1 for the short noise mode.
+    pub duty: u8,
+    // Length counter, or the sample bytes left for the DMC.
+    pub length: u16,
+    // Level fed to the mixer.
+    pub output: u8,
+}
+
 #[derive(Debug)]
 pub struct Apu {
     squares: (Square, Square),
     triangle: Triangle,
     noise: Noise,
-    cycle: u16,
-    step: usize,
-    sequencer_mode: bool,
-    enable_irq: bool,
+    dmc: Dmc,
+    frame_counter: FrameCounter,
+    // The pulse timers run at half the CPU clock.
+    is_odd_cycle: bool,
+
+    cpu_clock: usize,
+    sample_rate: usize,
+    resampler: Resampler,
+    samples: VecDeque<f32>,
+    // Kept over power cycles, so user settings stick.
+    pub mixer: Mixer,
+    filters: Vec<Filter>,
+    // Register writes are logged while set.
+    pub vgm: Option<VgmLogger>,
 }
 
 impl Apu {
-    pub fn new() -> Self {
+    pub fn new(region: Region) -> Self {
+        let (noise_table, dmc_table, frame_counter_steps) = match region {
+            Region::Pal => (
+                PAL_NOISE_TIMER_PERIOD_TABLE,
+                PAL_DMC_TIMER_PERIOD_TABLE,
+                PAL_FRAME_COUNTER_STEPS,
+            ),
+            // Dendy keeps the NTSC tables.
+            Region::Ntsc | Region::Dendy => (
+                NOISE_TIMER_PERIOD_TABLE,
+                DMC_TIMER_PERIOD_TABLE,
+                FRAME_COUNTER_STEPS,
+            ),
+        };
         Apu {
             squares: (Square::new(0), Square::new(1)),
-            triangle: Triangle::new(2),
-            noise: Noise::new(),
-            cycle: 0,
-            step: 0,
-            sequencer_mode: false,
-            enable_irq: false,
+            triangle: Triangle::new(),
+            noise: Noise::new(noise_table),
+            dmc: Dmc::new(dmc_table),
+            frame_counter: FrameCounter::new(frame_counter_steps),
+            is_odd_cycle: false,
+            cpu_clock: region.cpu_clock(),
+            sample_rate: SAMPLE_RATE,
+            resampler: Resampler::new(region.cpu_clock(), SAMPLE_RATE),
+            samples: VecDeque::with_capacity(SAMPLE_RATE),
+            mixer: Mixer::new(),
+            filters: output_filters(SAMPLE_RATE),
+            vgm: None,
         }
     }
 
     pub fn run(&mut self, cycle: u16) {
-        self.cycle += cycle;
-        if self.cycle >= DIVIDE_COUNT_FOR_240HZ {
-            // invoked by 240hz
-            self.cycle -= DIVIDE_COUNT_FOR_240HZ;
-            if self.sequencer_mode {
-                self.update_by_sequence_mode1();
-            } else {
-                self.update_by_sequence_mode0();
+        for _ in 0..cycle {
+            match self.frame_counter.run() {
+                Some(Clock::Quarter) => self.clock_quarter_frame(),
+                Some(Clock::Half) => {
+                    self.clock_quarter_frame();
+                    self.clock_half_frame();
+                }
+                None => (),
+            }
+            self.dmc.run();
+            self.triangle.clock_timer();
+            self.noise.clock_timer();
+            if self.is_odd_cycle {
+                self.squares.0.clock_timer();
+                self.squares.1.clock_timer();
             }
+            self.is_odd_cycle = !self.is_odd_cycle;
+            self.push_sample();
         }
+        if let Some(vgm) = &mut self.vgm {
+            vgm.run(cycle as u64);
+        }
+    }
+
+    // The reset button silences every channel as if $4015 was cleared.
+    // The frame counter restarts in the mode last written to $4017.
+    pub fn reset(&mut self) {
+        self.write(0x15, 0x00);
+        self.frame_counter.reset();
     }
 
     pub fn read(&mut self, addr: Addr) -> Data {
         match addr {
             0x15 => {
-                // self.interrupts.deassertIrq();
                 let s0 = if self.squares.0.has_count_end() {
                     0x00
                 } else {
@@ -70,13 +152,25 @@ impl Apu {
                 } else {
                     0x08
                 };
-                n | t | s1 | s0
+                let d = if self.dmc.has_count_end() { 0x00 } else { 0x10 };
+                let frame_irq = if self.frame_counter.is_irq() {
+                    0x40
+                } else {
+                    0x00
+                };
+                let dmc_irq = if self.dmc.is_irq() { 0x80 } else { 0x00 };
+                // Reading acknowledges the frame IRQ, but not the DMC one.
+                self.frame_counter.clear_irq();
+                dmc_irq | frame_irq | d | n | t | s1 | s0
             }
             _ => 0,
         }
     }
 
     pub fn write(&mut self, addr: Addr, data: Data) {
+        if let Some(vgm) = &mut self.vgm {
+            vgm.write(addr as u8, data);
+        }
         match addr {
             0x00..=0x03 => {
                 self.squares.0.write(addr, data);
@@ -90,6 +184,9 @@ impl Apu {
             0x0c..=0x0f => {
                 self.noise.write(addr - 0x0c, data);
             }
+            0x10..=0x13 => {
+                self.dmc.write(addr - 0x10, data);
+            }
             0x15 => {
                 if data & 0x01 == 0x01 {
                     self.squares.0.enable();
@@ -107,59 +204,209 @@ impl Apu {
                     self.triangle.disable();
                 }
                 if data & 0x08 == 0x08 {
-                    self.noise.start();
+                    self.noise.enable();
+                } else {
+                    self.noise.disable();
+                }
+                if data & 0x10 == 0x10 {
+                    self.dmc.enable();
                 } else {
-                    self.noise.stop();
+                    self.dmc.disable();
                 }
+                self.dmc.clear_irq();
             }
             0x17 => {
-                self.sequencer_mode = data & 0x80 == 0x80;
-                self.enable_irq = data & 0x40 == 0x40;
-                // if self.sequencer_mode {
-                self.step = 0;
-                self.cycle = 0;
-                // }
+                self.frame_counter.write(data, self.is_odd_cycle);
             }
             _ => (), //println!("addr {} data {}", addr, data),
         }
     }
 
-    fn update_by_sequence_mode0(&mut self) {
-        self.update_envelope();
-        if self.step % 2 == 1 {
-            self.update_counters();
-        }
-        self.step += 1;
-        if self.step == 4 {
-            if self.enable_irq {
-                //self.interrupts.assert_irq();
-            }
-            self.step = 0;
-        }
+    // Mixed output of the channels, 0.0 to about 1.0.
+    fn mix(&self) -> f32 {
+        let levels = [
+            self.squares.0.output(),
+            self.squares.1.output(),
+            self.triangle.output(),
+            self.noise.output(),
+            self.dmc.output(),
+        ];
+        // None of the mappers has expansion audio yet.
+        self.mixer.mix(levels, 0.0)
     }
 
-    fn update_by_sequence_mode1(&mut self) {
-        if self.step % 2 == 0 {
-            self.update_counters();
+    fn push_sample(&mut self) {
+        let amplitude = self.mix();
+        let sample = match self.resampler.clock(amplitude) {
+            Some(sample) => sample,
+            None => return,
+        };
+        // Keep about a second when the frontend does not take them.
+        if self.samples.len() >= self.sample_rate {
+            self.samples.pop_front();
         }
-        self.step += 1;
-        if self.step == 5 {
-            self.step = 0;
-        } else {
-            self.update_envelope();
+        let sample = self
+            .filters
+            .iter_mut()
+            .fold(sample, |sample, filter| filter.process(sample));
+        self.samples.push_back(sample);
+    }
+
+    // Moves the samples generated so far into `buf`, oldest first,
+    // and returns how many were written.
+    pub fn take_samples(&mut self, buf: &mut [f32]) -> usize {
+        let len = std::cmp::min(buf.len(), self.samples.len());
+        for (dst, src) in buf.iter_mut().zip(self.samples.drain(..len)) {
+            *dst = src;
         }
+        len
+    }
+
+    pub fn get_sample_rate(&self) -> usize {
+        self.sample_rate
     }
 
-    fn update_counters(&mut self) {
-        self.squares.0.update_counters();
-        self.squares.1.update_counters();
-        self.triangle.update_counter();
-        self.noise.update_counter();
+    // Samples already generated stay at the old rate.
+    pub fn set_sample_rate(&mut self, sample_rate: usize) {
+        self.sample_rate = sample_rate;
+        self.resampler.set_rates(self.cpu_clock, sample_rate as f64);
+        self.filters = output_filters(sample_rate);
     }
 
-    fn update_envelope(&mut self) {
-        self.squares.0.update_envelope();
-        self.squares.1.update_envelope();
-        self.noise.update_envelope();
+    // Generates `ratio` times as many samples, for small corrections that
+    // should not disturb the filters.
+    pub fn set_sample_rate_ratio(&mut self, ratio: f64) {
+        self.resampler
+            .set_rates(self.cpu_clock, self.sample_rate as f64 * ratio);
     }
+
+    // `None` for expansion audio, which no mapper has yet.
+    pub fn channel_state(&self, channel: Channel) -> Option<ChannelState> {
+        let cpu_clock = self.cpu_clock as f32;
+        // CPU cycles per waveform step for the tone channels.
+        let (mut state, steps) = match channel {
+            Channel::Pulse1 => (self.squares.0.state(), Some(16)),
+            Channel::Pulse2 => (self.squares.1.state(), Some(16)),
+            Channel::Triangle => (self.triangle.state(), Some(32)),
+            Channel::Noise => (self.noise.state(), None),
+            Channel::Dmc => (self.dmc.state(), None),
+            Channel::Expansion => return None,
+        };
+        state.frequency = match steps {
+            Some(steps) => cpu_clock / (steps * (state.period as u32 + 1)) as f32,
+            None => cpu_clock / state.period as f32,
+        };
+        Some(state)
+    }
+
+    pub fn is_irq(&self) -> bool {
+        self.dmc.is_irq() || self.frame_counter.is_irq()
+    }
+
+    // Address of the sample byte the DMC wants to fetch, if any.
+    // The bus answers by halting the CPU and calling `dmc_dma_complete`.
+    pub fn dmc_dma_request(&self) -> Option<Addr> {
+        self.dmc.dma_request()
+    }
+
+    pub fn dmc_dma_complete(&mut self, data: Data) {
+        self.dmc.dma_complete(data);
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.squares.0.clock_quarter_frame();
+        self.squares.1.clock_quarter_frame();
+        self.triangle.clock_quarter_frame();
+        self.noise.clock_quarter_frame();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.squares.0.clock_half_frame();
+        self.squares.1.clock_half_frame();
+        self.triangle.clock_half_frame();
+        self.noise.clock_half_frame();
+    }
+}
+
+#[test]
+fn test_samples() {
+    let mut apu = Apu::new(Region::Ntsc);
+    apu.write(0x15, 0x01);
+    // 50% duty, constant volume 15, about 440Hz.
+    apu.write(0x00, 0xBF);
+    apu.write(0x02, 0xFD);
+    apu.write(0x03, 0x00);
+    for _ in 0..apu.cpu_clock / 10 {
+        apu.run(1);
+    }
+    let mut buf = vec![0.0; SAMPLE_RATE];
+    let len = apu.take_samples(&mut buf);
+    assert!(len == SAMPLE_RATE / 10 || len == SAMPLE_RATE / 10 - 1);
+    assert_eq!(apu.take_samples(&mut buf), 0);
+    // 44 cycles of the square wave in 0.1s. The filters remove the DC
+    // offset and the level decays towards 0 between the edges.
+    let rising = buf[..len]
+        .windows(2)
+        .filter(|w| w[0] < 0.05 && w[1] >= 0.05)
+        .count();
+    assert!((43..=45).contains(&rising), "{}", rising);
+}
+
+#[test]
+fn test_frame_irq() {
+    let mut apu = Apu::new(Region::Ntsc);
+    apu.run(29827);
+    assert!(!apu.is_irq());
+    apu.run(3);
+    assert!(apu.is_irq());
+    assert_eq!(apu.read(0x15) & 0x40, 0x40);
+    assert_eq!(apu.read(0x15) & 0x40, 0x00);
+    assert!(!apu.is_irq());
+    // No IRQ in the 5-step mode.
+    apu.write(0x17, 0x80);
+    apu.run(60000);
+    assert!(!apu.is_irq());
+}
+
+#[test]
+fn test_five_step_mode_clocks_length() {
+    let mut apu = Apu::new(Region::Ntsc);
+    apu.write(0x15, 0x01);
+    apu.write(0x00, 0x00);
+    apu.write(0x03, 0x00);
+    // Every write that enters the 5-step mode clocks the length counter.
+    let mut writes = 0;
+    while apu.read(0x15) & 0x01 == 0x01 {
+        apu.write(0x17, 0x80);
+        apu.run(4);
+        writes += 1;
+    }
+    assert_eq!(writes, 10);
+}
+
+#[test]
+fn test_channel_state() {
+    let mut apu = Apu::new(Region::Ntsc);
+    apu.write(0x15, 0x0F);
+    // 50% duty, constant volume 12, period 253, length 254.
+    apu.write(0x04, 0xBC);
+    apu.write(0x06, 0xFD);
+    apu.write(0x07, 0x08);
+    let state = apu.channel_state(Channel::Pulse2).unwrap();
+    assert_eq!(state.period, 253);
+    assert!((state.frequency - 440.4).abs() < 0.1, "{}", state.frequency);
+    assert_eq!(state.duty, 2);
+    assert_eq!(state.volume, 12);
+    assert_eq!(state.length, 254);
+    // Same period an octave lower on the triangle.
+    apu.write(0x08, 0x81);
+    apu.write(0x0A, 0xFD);
+    apu.write(0x0B, 0x08);
+    let state = apu.channel_state(Channel::Triangle).unwrap();
+    assert!((state.frequency - 220.2).abs() < 0.1, "{}", state.frequency);
+    // Short noise mode, period 4.
+    apu.write(0x0E, 0x80);
+    let state = apu.channel_state(Channel::Noise).unwrap();
+    assert_eq!((state.period, state.duty), (4, 1));
+    assert_eq!(apu.channel_state(Channel::Expansion), None);
 }
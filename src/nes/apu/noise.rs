@@ -1,137 +1,149 @@
-use super::constants::{COUNTER_TABLE, CPU_CLOCK, GROBAL_GAIN, NOISE_TIMER_PERIOD_TABLE};
+use super::envelope::Envelope;
+use super::length_counter::LengthCounter;
+use super::ChannelState;
 use nes::types::{Addr, Data};
 
 #[derive(Debug)]
 pub struct Noise {
-    envelope_generator_counter: usize,
-    envelope_rate: usize,
-    envelope_volume: usize,
-    envelope_enable: bool,
-    is_length_counter_enable: bool,
-    length_counter: usize,
+    envelope: Envelope,
+    length_counter: LengthCounter,
 
     divider_for_frequency: usize,
-    frequency: usize,
-    enable: bool,
+    timer_period_table: &'static [u16],
+    timer: usize,
+    // 15 bit linear feedback shift register, bit 0 is the output.
+    shift_register: u16,
+    // Mode 1 takes the feedback from bit 6 for short, metallic loops.
+    is_short_mode: bool,
 }
 
-// extern "C" {
-//     fn set_noise_frequency(freq: f32);
-//     fn set_noise_volume(volume: f32);
-//     fn stop_noise();
-//     fn start_noise();
-//     // fn close_noise();
-// }
-
 impl Noise {
-    pub fn new() -> Self {
+    pub fn new(timer_period_table: &'static [u16]) -> Self {
         Noise {
-            envelope_generator_counter: 0,
-            envelope_rate: 0x0F,
-            envelope_volume: 0x0F,
-            envelope_enable: false,
-
-            is_length_counter_enable: false,
-            length_counter: 0,
-            divider_for_frequency: 1,
-            frequency: 0,
-            enable: false,
+            envelope: Envelope::new(),
+            length_counter: LengthCounter::new(),
+            divider_for_frequency: timer_period_table[0] as usize,
+            timer_period_table,
+            timer: 0,
+            shift_register: 1,
+            is_short_mode: false,
         }
     }
 
     pub fn enable(&mut self) {
-        self.enable = true;
-        self.start();
+        self.length_counter.set_enabled(true);
     }
 
     pub fn disable(&mut self) {
-        self.enable = false;
-        self.stop();
-    }
-
-    fn get_volume(&self) -> f32 {
-        let vol = if self.envelope_enable {
-            self.envelope_volume
-        } else {
-            self.envelope_rate
-        };
-        vol as f32 / (16.0 / GROBAL_GAIN)
+        self.length_counter.set_enabled(false);
     }
 
-    pub fn update_envelope(&mut self) {
-        self.envelope_generator_counter -= 1;
-        if self.envelope_generator_counter <= 0 {
-            self.envelope_generator_counter = self.envelope_rate;
-            if self.envelope_volume > 0 {
-                self.envelope_volume -= 1;
-            } else {
-                self.stop();
-                self.envelope_volume = 0x0F;
-            }
+    // The period table is in CPU cycles, so the timer is clocked every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
         }
-        self.set_volume();
+        self.timer = self.divider_for_frequency - 1;
+        let tap = if self.is_short_mode { 6 } else { 1 };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 0x01;
+        self.shift_register = (self.shift_register >> 1) | (feedback << 14);
     }
 
-    pub fn start(&self) {
-        // unsafe { start_noise() };
+    // Current level, 0 to 15.
+    pub fn output(&self) -> u8 {
+        if self.length_counter.is_zero() || self.shift_register & 0x01 == 0x01 {
+            return 0;
+        }
+        self.envelope.volume()
     }
 
-    pub fn stop(&self) {
-        // unsafe { stop_noise() };
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
     }
 
-    // Length counter
-    // When clocked by the frame counter, the length counter is decremented except when:
-    // The length counter is 0, or The halt flag is set
-    pub fn update_counter(&mut self) {
-        if self.is_length_counter_enable && self.length_counter > 0 {
-            self.length_counter -= 1;
-            if self.length_counter == 0 {
-                self.stop();
-            }
-        }
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
     }
 
     pub fn has_count_end(&self) -> bool {
-        self.length_counter == 0
+        self.length_counter.is_zero()
     }
 
-    fn set_volume(&self) {
-        // unsafe { set_noise_volume(self.get_volume()) }
+    pub fn state(&self) -> ChannelState {
+        ChannelState {
+            period: self.divider_for_frequency as u16,
+            frequency: 0.0,
+            volume: self.envelope.volume(),
+            duty: self.is_short_mode as u8,
+            length: self.length_counter.count() as u16,
+            output: self.output(),
+        }
     }
 
-    fn set_frequency(&self, data: Data) {
-        // unsafe {
-        //     set_noise_frequency(
-        //         CPU_CLOCK as f32 / NOISE_TIMER_PERIOD_TABLE[data as usize & 0xF] as f32 / 2f32,
-        //     )
-        // }
+    fn set_frequency(&mut self, data: Data) {
+        self.divider_for_frequency = self.timer_period_table[data as usize & 0xF] as usize;
     }
 
     pub fn write(&mut self, addr: Addr, data: Data) {
         match addr {
             0x00 => {
-                self.envelope_enable = (data & 0x10) == 0;
-                self.envelope_rate = data as usize & 0xF;
-                self.is_length_counter_enable = data & 0x20 == 0x00;
-                self.set_volume();
+                self.length_counter.set_halted(data & 0x20 == 0x20);
+                self.envelope.write(data);
             }
             0x02 => {
-                // this.isShortPeriod = !!(data & 0x80);
+                self.is_short_mode = data & 0x80 == 0x80;
                 self.set_frequency(data);
             }
             0x03 => {
-                if self.is_length_counter_enable {
-                    self.length_counter = COUNTER_TABLE[(data as usize & 0xF8) >> 3] as usize;
-                }
-                self.envelope_generator_counter = self.envelope_rate;
-                self.envelope_volume = 0x0F;
-                if self.enable {
-                    self.set_volume();
-                    self.start();
-                }
+                self.length_counter.load(data);
+                self.envelope.restart();
             }
             _ => (),
         }
     }
 }
+
+#[test]
+fn test_noise_lfsr() {
+    use super::constants::NOISE_TIMER_PERIOD_TABLE;
+    let mut noise = Noise::new(NOISE_TIMER_PERIOD_TABLE);
+    // The long mode repeats every 32767 steps, the short one every 93 or 31.
+    let period = |noise: &mut Noise| {
+        let start = noise.shift_register;
+        let mut steps = 0;
+        loop {
+            for _ in 0..4 {
+                noise.clock_timer();
+            }
+            steps += 1;
+            if noise.shift_register == start {
+                return steps;
+            }
+        }
+    };
+    assert_eq!(period(&mut noise), 32767);
+    noise.write(0x02, 0x80);
+    assert_eq!(period(&mut noise), 93);
+}
+
+#[test]
+fn test_noise_envelope_and_length() {
+    use super::constants::NOISE_TIMER_PERIOD_TABLE;
+    let mut noise = Noise::new(NOISE_TIMER_PERIOD_TABLE);
+    noise.enable();
+    // Decaying envelope without loop, length 2.
+    noise.write(0x00, 0x00);
+    noise.write(0x03, 0x18);
+    noise.clock_quarter_frame();
+    assert_eq!(noise.envelope.volume(), 15);
+    noise.clock_half_frame();
+    noise.clock_half_frame();
+    assert!(noise.has_count_end());
+    // The loop flag halts the length counter.
+    noise.write(0x00, 0x20);
+    noise.write(0x03, 0x18);
+    noise.clock_half_frame();
+    noise.clock_half_frame();
+    assert!(!noise.has_count_end());
+}
@@ -0,0 +1,210 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+// Taps of the band-limited step, centered on the step.
+const WIDTH: usize = 32;
+// Fractional positions the step kernel is computed for, the ones in
+// between are interpolated.
+const PHASES: usize = 64;
+// Cutoff relative to the output sample rate, a bit under Nyquist so
+// the transition band of the kernel stays below it.
+const CUTOFF: f64 = 0.4;
+
+// Converts the APU output, one value per CPU cycle, down to the host rate.
+// Like blip_buf, every change of the output is added to the buffer as a band
+// limited step at its exact position in time, so edges between two samples do
+// not alias. The buffer holds the differences and is summed on the way out.
+// Output lags behind by WIDTH / 2 samples.
+#[derive(Debug)]
+pub struct Resampler {
+    // Output samples per clock.
+    step: f64,
+    // Position of the current clock after the oldest buffered sample.
+    time: f64,
+    buf: VecDeque<f64>,
+    kernel: Vec<[f64; WIDTH]>,
+    integrator: f64,
+    amplitude: f32,
+}
+
+impl Resampler {
+    pub fn new(clock_rate: usize, sample_rate: usize) -> Self {
+        Resampler {
+            step: sample_rate as f64 / clock_rate as f64,
+            time: 0.0,
+            buf: vec![0.0; WIDTH + 1].into_iter().collect(),
+            kernel: (0..=PHASES).map(step_kernel).collect(),
+            integrator: 0.0,
+            amplitude: 0.0,
+        }
+    }
+
+    // Changing the rates keeps the samples in flight. The sample rate may be
+    // fractional to follow the host clock.
+    pub fn set_rates(&mut self, clock_rate: usize, sample_rate: f64) {
+        self.step = sample_rate / clock_rate as f64;
+    }
+
+    // Takes the output for one clock and returns a sample when one is complete.
+    pub fn clock(&mut self, amplitude: f32) -> Option<f32> {
+        if amplitude != self.amplitude {
+            let delta = (amplitude - self.amplitude) as f64;
+            self.amplitude = amplitude;
+            let position = self.time * PHASES as f64;
+            let phase = position as usize;
+            let fraction = position - phase as f64;
+            let (a, b) = (&self.kernel[phase], &self.kernel[phase + 1]);
+            for (i, dst) in self.buf.iter_mut().take(WIDTH).enumerate() {
+                *dst += delta * (a[i] + (b[i] - a[i]) * fraction);
+            }
+        }
+        self.time += self.step;
+        if self.time < 1.0 {
+            return None;
+        }
+        self.time -= 1.0;
+        self.integrator += self.buf.pop_front().unwrap_or(0.0);
+        self.buf.push_back(0.0);
+        Some(self.integrator as f32)
+    }
+}
+
+// Differences of a windowed-sinc step starting `phase / PHASES` samples after
+// the center tap. The taps add up to 1 so the step reaches its full height.
+fn step_kernel(phase: usize) -> [f64; WIDTH] {
+    let half = (WIDTH / 2) as f64;
+    let offset = phase as f64 / PHASES as f64;
+    let mut kernel = [0.0; WIDTH];
+    for (i, tap) in kernel.iter_mut().enumerate() {
+        let t = i as f64 - half - offset;
+        if t <= -half {
+            continue;
+        }
+        let x = 2.0 * CUTOFF * t;
+        let sinc = if x == 0.0 {
+            1.0
+        } else {
+            (PI * x).sin() / (PI * x)
+        };
+        // Blackman window over -half..half.
+        let w = (t + half) / (2.0 * half);
+        let window = 0.42 - 0.5 * (2.0 * PI * w).cos() + 0.08 * (4.0 * PI * w).cos();
+        *tap = sinc * window;
+    }
+    let sum: f64 = kernel.iter().sum();
+    for tap in kernel.iter_mut() {
+        *tap /= sum;
+    }
+    kernel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLOCK_RATE: usize = 1_789_773;
+    const SAMPLE_RATE: usize = 44100;
+    const LEN: usize = 4096;
+
+    // About 5kHz, so the 3rd harmonic is in the passband and the rest
+    // is above Nyquist and folds back when it is not filtered.
+    const HALF_PERIOD: usize = 179;
+
+    fn square(clock: usize) -> f32 {
+        if (clock / HALF_PERIOD) % 2 == 0 {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    // Power that is not at the harmonics of the square, relative to the
+    // power that is, from a DFT with a Blackman-Harris window, which leaks
+    // far less than the aliasing being measured.
+    fn alias_ratio(samples: &[f32]) -> f64 {
+        let len = samples.len();
+        let mean = samples.iter().map(|&s| s as f64).sum::<f64>() / len as f64;
+        let windowed: Vec<f64> = samples
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| {
+                let x = 2.0 * PI * i as f64 / len as f64;
+                let w = 0.35875 - 0.48829 * x.cos() + 0.14128 * (2.0 * x).cos()
+                    - 0.01168 * (3.0 * x).cos();
+                (s as f64 - mean) * w
+            })
+            .collect();
+        let fundamental = CLOCK_RATE as f64 / (2 * HALF_PERIOD) as f64;
+        let bin_hz = SAMPLE_RATE as f64 / len as f64;
+        let harmonics: Vec<f64> = (0..)
+            .map(|n| fundamental * (2 * n + 1) as f64 / bin_hz)
+            .take_while(|&bin| bin < (len / 2) as f64)
+            .collect();
+        let (mut signal, mut alias) = (0.0, 0.0);
+        // Leave out the bins next to DC, where the window leaks the mean.
+        for k in 8..len / 2 {
+            let (mut re, mut im) = (0.0, 0.0);
+            for (i, s) in windowed.iter().enumerate() {
+                let angle = 2.0 * PI * (k * i % len) as f64 / len as f64;
+                re += s * angle.cos();
+                im -= s * angle.sin();
+            }
+            let power = re * re + im * im;
+            if harmonics.iter().any(|&bin| (k as f64 - bin).abs() < 8.0) {
+                signal += power;
+            } else {
+                alias += power;
+            }
+        }
+        alias / signal
+    }
+
+    #[test]
+    fn test_band_limited_square() {
+        let mut resampler = Resampler::new(CLOCK_RATE, SAMPLE_RATE);
+        let mut samples = vec![];
+        let mut clock = 0;
+        while samples.len() < LEN + WIDTH {
+            if let Some(sample) = resampler.clock(square(clock)) {
+                samples.push(sample);
+            }
+            clock += 1;
+        }
+        // Less than -70dB of aliasing.
+        let ratio = alias_ratio(&samples[WIDTH..]);
+        assert!(ratio < 1e-7, "{}", ratio);
+    }
+
+    #[test]
+    fn test_point_sampled_square_aliases() {
+        // Taking every n-th value, for comparison.
+        let samples: Vec<f32> = (0..LEN)
+            .map(|i| square(i * CLOCK_RATE / SAMPLE_RATE))
+            .collect();
+        let ratio = alias_ratio(&samples);
+        assert!(ratio > 1e-2, "{}", ratio);
+    }
+
+    #[test]
+    fn test_dc_and_rate_change() {
+        let mut resampler = Resampler::new(CLOCK_RATE, SAMPLE_RATE);
+        let mut last = 0.0;
+        let mut count = 0;
+        for _ in 0..CLOCK_RATE / 10 {
+            if let Some(sample) = resampler.clock(0.5) {
+                last = sample;
+                count += 1;
+            }
+        }
+        assert!((last - 0.5).abs() < 1e-6, "{}", last);
+        assert!((4409..=4410).contains(&count), "{}", count);
+        resampler.set_rates(CLOCK_RATE, 48000.0);
+        count = 0;
+        for _ in 0..CLOCK_RATE / 10 {
+            if resampler.clock(0.5).is_some() {
+                count += 1;
+            }
+        }
+        assert!((4799..=4801).contains(&count), "{}", count);
+    }
+}
@@ -0,0 +1,119 @@
+use nes::types::Data;
+
+// Bends the pulse period up or down every few half frames ($4001, $4005).
+// The target period is computed all the time, and the channel is muted when
+// it overflows $7FF, even with the sweep disabled.
+#[derive(Debug)]
+pub struct Sweep {
+    is_enabled: bool,
+    divider_period: u8,
+    is_negate: bool,
+    shift: u8,
+    // Pulse 1 negates with ones' complement, so it subtracts 1 more than pulse 2.
+    is_ones_complement: bool,
+    is_reload: bool,
+    divider: u8,
+}
+
+impl Sweep {
+    pub fn new(is_ones_complement: bool) -> Self {
+        Sweep {
+            is_enabled: false,
+            divider_period: 0,
+            is_negate: false,
+            shift: 0,
+            is_ones_complement,
+            is_reload: false,
+            divider: 0,
+        }
+    }
+
+    pub fn write(&mut self, data: Data) {
+        self.is_enabled = data & 0x80 == 0x80;
+        self.divider_period = (data >> 4) & 0x07;
+        self.is_negate = data & 0x08 == 0x08;
+        self.shift = data & 0x07;
+        self.is_reload = true;
+    }
+
+    pub fn target_period(&self, period: u16) -> u16 {
+        let change = period >> self.shift;
+        if !self.is_negate {
+            return period + change;
+        }
+        if self.is_ones_complement {
+            period.saturating_sub(change + 1)
+        } else {
+            period.saturating_sub(change)
+        }
+    }
+
+    pub fn is_muting(&self, period: u16) -> bool {
+        period < 8 || self.target_period(period) > 0x7FF
+    }
+
+    // Clocked by the frame counter every half frame, may update `period`.
+    pub fn clock(&mut self, period: &mut u16) {
+        if self.divider == 0 && self.is_enabled && self.shift > 0 && !self.is_muting(*period) {
+            *period = self.target_period(*period);
+        }
+        if self.divider == 0 || self.is_reload {
+            self.divider = self.divider_period;
+            self.is_reload = false;
+        } else {
+            self.divider -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negate() {
+        // Shift 1, negate.
+        let mut pulse1 = Sweep::new(true);
+        let mut pulse2 = Sweep::new(false);
+        pulse1.write(0x89);
+        pulse2.write(0x89);
+        assert_eq!(pulse1.target_period(0x100), 0x7F);
+        assert_eq!(pulse2.target_period(0x100), 0x80);
+        // Shift 1, up.
+        pulse1.write(0x81);
+        assert_eq!(pulse1.target_period(0x100), 0x180);
+    }
+
+    #[test]
+    fn muting() {
+        let mut sweep = Sweep::new(false);
+        // Disabled, but the target period still overflows.
+        sweep.write(0x00);
+        assert!(sweep.is_muting(0x400));
+        assert!(!sweep.is_muting(0x3FF));
+        assert!(sweep.is_muting(7));
+        // Going down never overflows.
+        sweep.write(0x08);
+        assert!(!sweep.is_muting(0x7FF));
+    }
+
+    #[test]
+    fn divider() {
+        let mut sweep = Sweep::new(false);
+        // Enabled, period 1 so every other half frame, shift 2, up.
+        sweep.write(0x92);
+        let mut period = 0x100;
+        // The reload does not keep the first clock from updating.
+        sweep.clock(&mut period);
+        assert_eq!(period, 0x140);
+        sweep.clock(&mut period);
+        assert_eq!(period, 0x140);
+        sweep.clock(&mut period);
+        assert_eq!(period, 0x190);
+        // Not updated while it would mute.
+        let mut period = 0x700;
+        sweep.write(0x81);
+        sweep.clock(&mut period);
+        assert_eq!(period, 0x700);
+    }
+}
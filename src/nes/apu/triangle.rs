@@ -1,146 +1,174 @@
-use super::constants::*;
+use super::length_counter::LengthCounter;
+use super::ChannelState;
 use nes::types::{Addr, Data};
 
 #[derive(Debug)]
 pub struct Triangle {
-    index: usize,
-    is_length_counter_enable: bool,
-    length_counter: usize,
-    linear_counter: usize,
-    divider_for_frequency: usize,
-    frequency: usize,
-    enable: bool,
-    playing: bool,
+    length_counter: LengthCounter,
+    // Finer grained than the length counter, in quarter frames.
+    linear_counter: u8,
+    linear_counter_reload: u8,
+    is_linear_counter_reload: bool,
+    // Also halts the length counter.
+    is_control: bool,
+    // 11 bit timer period, in CPU cycles.
+    period: u16,
+    timer: u16,
+    sequence_step: usize,
 }
 
-// extern "C" {
-//     fn start_oscillator(index: usize);
-//     fn stop_oscillator(index: usize);
-//     fn close_oscillator(index: usize);
-//     fn set_oscillator_frequency(index: usize, freq: usize);
-//     fn change_oscillator_frequency(index: usize, freq: usize);
-//     fn set_oscillator_volume(index: usize, volume: f32);
-//     fn set_oscillator_pulse_width(index: usize, width: f32);
-// }
+// 15 down to 0, then back up.
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+    13, 14, 15,
+];
 
 impl Triangle {
-    pub fn new(index: usize) -> Self {
+    pub fn new() -> Self {
         Triangle {
-            index,
-            is_length_counter_enable: false,
-            length_counter: 0,
+            length_counter: LengthCounter::new(),
             linear_counter: 0,
-            divider_for_frequency: 1,
-            frequency: 0,
-            enable: false,
-            playing: false,
+            linear_counter_reload: 0,
+            is_linear_counter_reload: false,
+            is_control: false,
+            period: 0,
+            timer: 0,
+            sequence_step: 0,
         }
     }
 
-    fn get_volume(&self) -> f32 {
-        32.0 / (16.0 / GROBAL_GAIN)
-    }
-
-    fn stop_oscillator(&mut self) {
-        // self.length_counter = 0;
-        // self.linear_counter = 0;
-        // unsafe {
-        //     stop_oscillator(self.index);
-        //     set_oscillator_volume(self.index, 0.0);
-        // };
-    }
-
-    pub fn enable(&mut self) {
-        self.enable = true;
-        self.start();
-    }
-
-    pub fn disable(&mut self) {
-        self.enable = false;
-        self.stop();
+    // The timer is clocked every CPU cycle. The sequencer only moves while
+    // both counters are non-zero, so a stopped triangle holds its level
+    // instead of dropping to 0, which would pop.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            // Ultrasonic periods are left out, they would only add noise.
+            if !self.length_counter.is_zero() && self.linear_counter > 0 && self.period >= 2 {
+                self.sequence_step = (self.sequence_step + 1) & 0x1F;
+            }
+        } else {
+            self.timer -= 1;
+        }
     }
 
-    pub fn stop(&mut self) {
-        if self.playing {
-            self.playing = false;
-            self.stop_oscillator();
-        }
+    // Current level, 0 to 15.
+    pub fn output(&self) -> u8 {
+        SEQUENCE[self.sequence_step]
     }
 
-    // Length counter
-    // When clocked by the frame counter, the length counter is decremented except when:
-    // The length counter is 0, or The halt flag is set
-    pub fn update_counter(&mut self) {
-        if self.is_length_counter_enable && self.length_counter > 0 {
-            self.length_counter -= 1;
-        }
-        if self.linear_counter > 0 {
+    pub fn clock_quarter_frame(&mut self) {
+        if self.is_linear_counter_reload {
+            self.linear_counter = self.linear_counter_reload;
+        } else if self.linear_counter > 0 {
             self.linear_counter -= 1;
         }
-        if !self.is_length_counter_enable {
-            return;
-        }
-        if self.length_counter == 0 || self.linear_counter == 0 {
-            self.stop();
+        if !self.is_control {
+            self.is_linear_counter_reload = false;
         }
     }
 
-    fn change_frequency(&self) {
-        // unsafe {
-        //     change_oscillator_frequency(self.index, self.frequency);
-        // }
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
     }
 
-    pub fn start(&mut self) {
-        if !self.playing {
-            self.playing = true;
-            // unsafe {
-            //     start_oscillator(self.index);
-            //     set_oscillator_frequency(self.index, self.frequency);
-            // };
-        } else {
-            self.change_frequency();
-        }
+    pub fn enable(&mut self) {
+        self.length_counter.set_enabled(true);
+    }
+
+    pub fn disable(&mut self) {
+        self.length_counter.set_enabled(false);
     }
 
     pub fn has_count_end(&self) -> bool {
-        self.length_counter == 0
+        self.length_counter.is_zero()
     }
 
-    fn set_volume(&self) {
-        // unsafe { set_oscillator_volume(self.index, self.get_volume()) }
+    // There is no volume control, it is reported as 15 while the sequencer runs.
+    pub fn state(&self) -> ChannelState {
+        let is_running = !self.length_counter.is_zero() && self.linear_counter > 0;
+        ChannelState {
+            period: self.period,
+            frequency: 0.0,
+            volume: if is_running { 15 } else { 0 },
+            duty: 0,
+            length: self.length_counter.count() as u16,
+            output: self.output(),
+        }
     }
 
     pub fn write(&mut self, addr: Addr, data: Data) {
         match addr {
             0x00 => {
-                self.is_length_counter_enable = data & 0x80 == 0;
-                self.linear_counter = data as usize & 0x7F;
+                self.is_control = data & 0x80 == 0x80;
+                self.length_counter.set_halted(self.is_control);
+                self.linear_counter_reload = data & 0x7F;
             }
             0x02 => {
-                self.divider_for_frequency &= 0x700;
-                self.divider_for_frequency |= data as usize;
-                self.update_frequency();
-                self.change_frequency();
+                self.period = (self.period & 0x700) | data as u16;
             }
             0x03 => {
-                // Programmable timer, length counter
-                self.divider_for_frequency &= 0xFF;
-                self.divider_for_frequency |= (data as usize & 0x7) << 8;
-                if self.is_length_counter_enable {
-                    self.length_counter = COUNTER_TABLE[(data & 0xF8) as usize >> 3] as usize / 2;
-                }
-                self.update_frequency();
-                self.set_volume();
-                if self.enable {
-                    self.start();
-                }
+                self.period = (self.period & 0xFF) | (data as u16 & 0x07) << 8;
+                self.length_counter.load(data);
+                self.is_linear_counter_reload = true;
             }
             _ => (),
         }
     }
+}
+
+#[test]
+fn test_triangle_sequence() {
+    let mut triangle = Triangle::new();
+    triangle.enable();
+    triangle.write(0x00, 0x7F);
+    triangle.write(0x02, 0x02);
+    triangle.write(0x03, 0x08);
+    // Nothing moves until the linear counter is loaded.
+    triangle.clock_timer();
+    triangle.clock_timer();
+    triangle.clock_timer();
+    assert_eq!(triangle.output(), 15);
+    triangle.clock_quarter_frame();
+    let mut levels = vec![triangle.output()];
+    for _ in 0..32 * 3 {
+        triangle.clock_timer();
+        if *levels.last().unwrap() != triangle.output() {
+            levels.push(triangle.output());
+        }
+    }
+    assert_eq!(&levels[0..4], &[15, 14, 13, 12]);
+    assert_eq!(&levels[14..19], &[1, 0, 1, 2, 3]);
+    // Holds while stopped.
+    triangle.disable();
+    let level = triangle.output();
+    triangle.clock_timer();
+    triangle.clock_timer();
+    triangle.clock_timer();
+    assert_eq!(triangle.output(), level);
+}
 
-    fn update_frequency(&mut self) {
-        self.frequency = CPU_CLOCK / ((self.divider_for_frequency + 1) * 32) as usize;
+#[test]
+fn test_triangle_linear_counter() {
+    let mut triangle = Triangle::new();
+    triangle.enable();
+    // Control clear, reload 2.
+    triangle.write(0x00, 0x02);
+    triangle.write(0x03, 0x00);
+    triangle.clock_quarter_frame();
+    assert_eq!(triangle.linear_counter, 2);
+    // The reload flag was cleared, so it counts down.
+    triangle.clock_quarter_frame();
+    triangle.clock_quarter_frame();
+    triangle.clock_quarter_frame();
+    assert_eq!(triangle.linear_counter, 0);
+    // With control set the reload flag sticks, and the length counter halts.
+    triangle.write(0x00, 0x82);
+    triangle.write(0x03, 0x00);
+    for _ in 0..4 {
+        triangle.clock_quarter_frame();
+        triangle.clock_half_frame();
     }
+    assert_eq!(triangle.linear_counter, 2);
+    assert!(!triangle.has_count_end());
 }
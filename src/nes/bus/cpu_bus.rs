@@ -5,7 +5,13 @@ use nes::mmc::*;
 use nes::ppu::Ppu;
 use nes::ram::Ram;
 use nes::rom::Rom;
+use nes::types::{Addr, Data};
 
+// Every CPU cycle is a bus access, so the bus is what keeps the rest of the
+// console in step with the CPU: each access advances the PPU by 3 dots (3.2 on
+// PAL) and the APU by one cycle, and DMA halts the CPU on its next read.
+// The data bus keeps the last value transferred on it ("open bus"), which is
+// what the CPU reads from addresses nothing responds to.
 pub struct Bus<'a> {
     program_rom: &'a Rom,
     work_ram: &'a mut Ram,
@@ -14,6 +20,9 @@ pub struct Bus<'a> {
     keypad: &'a mut Keypad,
     dma: &'a mut Dma,
     mmc: &'a mut Mmc,
+    cycles: &'a mut u64,
+    open_bus: &'a mut Data,
+    frame_ready: bool,
 }
 
 pub trait CpuBus {
@@ -22,9 +31,19 @@ pub trait CpuBus {
     fn read(&mut self, addr: u16) -> u8;
 
     fn write(&mut self, addr: u16, data: u8);
+
+    fn take_nmi(&mut self) -> bool {
+        false
+    }
+
+    // IRQ is level triggered, it is taken while asserted and the I flag is clear.
+    fn is_irq(&mut self) -> bool {
+        false
+    }
 }
 
 impl<'a> Bus<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         program_rom: &'a Rom,
         work_ram: &'a mut Ram,
@@ -33,6 +52,8 @@ impl<'a> Bus<'a> {
         keypad: &'a mut Keypad,
         dma: &'a mut Dma,
         mmc: &'a mut Mmc,
+        cycles: &'a mut u64,
+        open_bus: &'a mut Data,
     ) -> Bus<'a> {
         Self {
             program_rom,
@@ -42,59 +63,170 @@ impl<'a> Bus<'a> {
             keypad,
             dma,
             mmc,
+            cycles,
+            open_bus,
+            frame_ready: false,
         }
     }
-}
 
-impl<'a> CpuBus for Bus<'a> {
-    fn read_word(&mut self, addr: u16) -> u16 {
-        let lower = self.read(addr) as u16;
-        let upper = self.read(addr + 1) as u16;
-        (upper << 8 | lower) as u16
+    pub fn is_frame_ready(&self) -> bool {
+        self.frame_ready
     }
 
-    fn read(&mut self, addr: u16) -> u8 {
-        match addr {
-            0x0000..=0x1FFF => self.work_ram.read(addr & 0x07FF),
-            0x2000..=0x3FFF => self.ppu.read(addr - 0x2000),
-            0x4016 => self.keypad.read(),
-            0x4017 => 0, // TODO: 2player
-            0x4000..=0x401F => self.apu.read(addr - 0x4000),
-            0x6000..=0x7FFF => {
-                println!(
-                    "Not implemented. This area is battery backup ram area 0x{:x}",
-                    addr
-                );
-                0
+    #[cfg(test)]
+    pub fn get_cycles(&self) -> u64 {
+        *self.cycles
+    }
+
+    fn tick(&mut self) {
+        if self.ppu.run_cpu_cycle(self.mmc) {
+            self.frame_ready = true;
+        }
+        self.apu.run(1);
+        *self.cycles += 1;
+    }
+
+    // The PPU takes a register write in the middle of the CPU cycle, with
+    // some of the dots of the cycle already run.
+    fn tick_ppu_write(&mut self, addr: Addr, data: Data) {
+        let (before, dots) = self.ppu.start_cpu_cycle();
+        if self.ppu.run(before, self.mmc) {
+            self.frame_ready = true;
+        }
+        self.write_data(addr, data);
+        if self.ppu.run(dots - before, self.mmc) {
+            self.frame_ready = true;
+        }
+        self.apu.run(1);
+        *self.cycles += 1;
+    }
+
+    // DMA reads on "get" cycles and writes on "put" cycles, which alternate.
+    fn is_get_cycle(&self) -> bool {
+        *self.cycles & 1 == 0
+    }
+
+    // The CPU is halted on the read it was about to do, and that read is
+    // repeated. Reading $4016 twice in a row clocks the controller twice,
+    // which is how DMC DMA deletes controller bits.
+    fn halt(&mut self, addr: Addr) {
+        self.tick();
+        self.read_data(addr);
+    }
+
+    // 513 cycles, or 514 when it has to wait a cycle to start on a get.
+    fn run_oam_dma(&mut self, addr: Addr) {
+        let page = self.dma.start();
+        self.halt(addr);
+        let mut latch: Option<Data> = None;
+        let mut index: Addr = 0;
+        while index < 0x100 {
+            let is_get = self.is_get_cycle();
+            self.tick();
+            if is_get {
+                // DMC DMA takes priority for the get cycle, so a sample fetch
+                // during OAM DMA only costs 2 cycles.
+                if let Some(dmc_addr) = self.apu.dmc_dma_request() {
+                    let data = self.read_data(dmc_addr);
+                    self.apu.dmc_dma_complete(data);
+                } else {
+                    latch = Some(self.read_data(page | index));
+                }
+            } else if let Some(data) = latch.take() {
+                self.write_data(0x2004, data);
+                index += 1;
             }
+        }
+    }
+
+    // Halt, dummy and alignment cycles, then the get: 3 or 4 cycles.
+    fn run_dmc_dma(&mut self, addr: Addr, dmc_addr: Addr) {
+        self.halt(addr);
+        self.tick();
+        if !self.is_get_cycle() {
+            self.tick();
+        }
+        self.tick();
+        let data = self.read_data(dmc_addr);
+        self.apu.dmc_dma_complete(data);
+    }
+
+    fn read_data(&mut self, addr: Addr) -> Data {
+        let open_bus = *self.open_bus;
+        let data = match addr {
+            0x0000..=0x1FFF => self.work_ram.read(addr & 0x07FF),
+            // The 8 PPU registers are mirrored every 8 bytes.
+            0x2000..=0x3FFF => self.ppu.read(addr & 0x0007, self.mmc),
+            // $4015 is inside the CPU, reading it does not drive the external bus.
+            0x4015 => return self.apu.read(0x15) | (open_bus & 0x20),
+            // Controllers only drive the low bits.
+            0x4016 => (open_bus & 0xE0) | self.keypad.read(0),
+            0x4017 => (open_bus & 0xE0) | self.keypad.read(1),
+            // Write only APU registers, the expansion area and the
+            // missing battery backup ram.
+            0x4000..=0x7FFF => open_bus,
             0x8000..=0xBFFF => self.program_rom.read(addr - 0x8000),
             0xC000..=0xFFFF if self.program_rom.size() <= 0x4000 => {
                 // println!("rom: {:?}", self.program_rom);
                 self.program_rom.read(addr - 0xC000)
             }
             0xC000..=0xFFFF => self.program_rom.read(addr - 0x8000),
-            _ => panic!("[READ] There is an illegal address (0x{:x}) access.", addr),
-        }
+        };
+        *self.open_bus = data;
+        data
     }
 
-    fn write(&mut self, addr: u16, data: u8) {
+    fn write_data(&mut self, addr: Addr, data: Data) {
+        *self.open_bus = data;
         match addr {
             0x0000..=0x1FFF => self.work_ram.write(addr & 0x07FF, data),
-            0x2000..=0x3FFF => self.ppu.write(addr - 0x2000, data),
+            0x2000..=0x3FFF => self.ppu.write(addr & 0x0007, data, self.mmc),
             0x4014 => self.dma.write(data),
             0x4016 => self.keypad.write(data),
             0x4000..=0x401F => self.apu.write(addr - 0x4000, data),
-            0x6000..=0x7FFF => {
-                println!(
-                    "Not implemented. This area is battery backup ram area 0x{:x}",
-                    addr
-                );
-            }
-            0x8000..=0xFFFF => {
-                println!("switch bank to {}", data);
-                self.mmc.set_bank(data);
-            }
-            _ => panic!("[WRITE] There is an illegal address (0x{:x}) access.", addr),
+            0x8000..=0xFFFF => self.mmc.set_bank(data),
+            // Expansion area and battery backed RAM, nothing is connected.
+            _ => (),
         };
     }
 }
+
+impl<'a> CpuBus for Bus<'a> {
+    fn read_word(&mut self, addr: u16) -> u16 {
+        let lower = self.read(addr) as u16;
+        let upper = self.read(addr.wrapping_add(1)) as u16;
+        upper << 8 | lower
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        // DMA can only halt the CPU on a read cycle.
+        if self.dma.should_run() {
+            self.run_oam_dma(addr);
+        }
+        if let Some(dmc_addr) = self.apu.dmc_dma_request() {
+            self.run_dmc_dma(addr, dmc_addr);
+        }
+        self.tick();
+        let data = self.read_data(addr);
+        self.ppu.poll_nmi();
+        data
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        if let 0x2000..=0x3FFF = addr {
+            self.tick_ppu_write(addr, data);
+        } else {
+            self.tick();
+            self.write_data(addr, data);
+        }
+        self.ppu.poll_nmi();
+    }
+
+    fn take_nmi(&mut self) -> bool {
+        self.ppu.take_nmi()
+    }
+
+    fn is_irq(&mut self) -> bool {
+        self.apu.is_irq()
+    }
+}
@@ -2,6 +2,7 @@ use super::super::bus::cpu_bus::CpuBus;
 use super::super::cpu_registers::CpuRegisters;
 use super::super::types::{Addr, Data, Word};
 use super::opecode::*;
+use super::Variant;
 
 pub fn fetch<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) -> Data {
     let code = bus.read(registers.get_PC());
@@ -11,23 +12,34 @@ pub fn fetch<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) -> Data
 
 pub fn fetch_operand<T: CpuRegisters, U: CpuBus>(
     code: &Opecode,
+    variant: Variant,
     registers: &mut T,
     bus: &mut U,
 ) -> Word {
     match code.mode {
-        Addressing::Accumulator => 0x0000,
-        Addressing::Implied => 0x0000,
+        Addressing::Accumulator | Addressing::Implied => {
+            // One byte instructions still read the byte after the opcode.
+            bus.read(registers.get_PC());
+            0x0000
+        }
         Addressing::Immediate => fetch(registers, bus) as Word,
         Addressing::Relative => fetch_relative(registers, bus),
         Addressing::ZeroPage => fetch(registers, bus) as Word,
         Addressing::ZeroPageX => fetch_zeropage_x(registers, bus),
         Addressing::ZeroPageY => fetch_zeropage_y(registers, bus),
         Addressing::Absolute => fetch_word(registers, bus),
-        Addressing::AbsoluteX => fetch_absolute_x(registers, bus),
-        Addressing::AbsoluteY => fetch_absolute_y(registers, bus),
+        Addressing::AbsoluteX => fetch_absolute_x(code.name.is_write(), registers, bus),
+        Addressing::AbsoluteY => fetch_absolute_y(code.name.is_write(), registers, bus),
         Addressing::PreIndexedIndirect => fetch_pre_indexed_indirect(registers, bus),
-        Addressing::PostIndexedIndirect => fetch_post_indexed_indirect(registers, bus),
+        Addressing::PostIndexedIndirect => {
+            fetch_post_indexed_indirect(code.name.is_write(), registers, bus)
+        }
+        Addressing::IndirectAbsolute if variant == Variant::Cmos65C02 => {
+            fetch_indirect_absolute_fixed(registers, bus)
+        }
         Addressing::IndirectAbsolute => fetch_indirect_absolute(registers, bus),
+        Addressing::ZeroPageIndirect => fetch_zeropage_indirect(registers, bus),
+        Addressing::AbsoluteXIndirect => fetch_absolute_x_indirect(registers, bus),
     }
 }
 
@@ -42,53 +54,101 @@ pub fn fetch_word<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) ->
 pub fn fetch_relative<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) -> Word {
     let base = fetch(registers, bus) as Word;
     if base < 0x80 {
-        base + registers.get_PC()
+        base.wrapping_add(registers.get_PC())
     } else {
-        base + registers.get_PC() - 256
+        base.wrapping_add(registers.get_PC()).wrapping_sub(256)
     }
 }
 
 pub fn fetch_zeropage_x<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) -> Word {
     let addr = fetch(registers, bus) as Word;
-    (addr + registers.get_X() as Word) & 0xFF as Word
+    // The base address is read while the index is added.
+    bus.read(addr);
+    (addr + registers.get_X() as Word) & 0xFF
 }
 
 pub fn fetch_zeropage_y<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) -> Word {
     let addr = fetch(registers, bus) as Word;
-    (addr + registers.get_Y() as Word) & 0xFF as Word
+    bus.read(addr);
+    (addr + registers.get_Y() as Word) & 0xFF
 }
 
-pub fn fetch_absolute_x<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) -> Word {
-    let addr = fetch_word(registers, bus);
-    (addr + registers.get_X() as Word) & 0xFFFF
+pub fn fetch_absolute_x<T: CpuRegisters, U: CpuBus>(
+    is_write: bool,
+    registers: &mut T,
+    bus: &mut U,
+) -> Word {
+    let base = fetch_word(registers, bus);
+    index_with_carry(base, registers.get_X(), is_write, bus)
 }
 
-pub fn fetch_absolute_y<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) -> Word {
-    let addr = fetch_word(registers, bus);
-    (addr + registers.get_Y() as Word) & 0xFFFF
+pub fn fetch_absolute_y<T: CpuRegisters, U: CpuBus>(
+    is_write: bool,
+    registers: &mut T,
+    bus: &mut U,
+) -> Word {
+    let base = fetch_word(registers, bus);
+    index_with_carry(base, registers.get_Y(), is_write, bus)
 }
 
 pub fn fetch_pre_indexed_indirect<T: CpuRegisters, U: CpuBus>(
     registers: &mut T,
     bus: &mut U,
 ) -> Word {
-    let addr = fetch(registers, bus).wrapping_add(registers.get_X()) as Addr;
-    let addr = (bus.read(addr) as Addr) + ((bus.read((addr + 1) as Addr & 0xFF) as Addr) << 8);
-    addr & 0xFFFF
+    let pointer = fetch(registers, bus);
+    bus.read(pointer as Addr);
+    let addr = pointer.wrapping_add(registers.get_X()) as Addr;
+    (bus.read(addr) as Addr) | ((bus.read((addr + 1) & 0xFF) as Addr) << 8)
 }
 
 pub fn fetch_post_indexed_indirect<T: CpuRegisters, U: CpuBus>(
+    is_write: bool,
     registers: &mut T,
     bus: &mut U,
 ) -> Word {
     let addr = fetch(registers, bus) as Addr;
-    let base_addr = (bus.read(addr) as usize) + ((bus.read((addr + 1) & 0x00FF) as usize) * 0x100);
-    ((base_addr + (registers.get_Y() as usize)) & 0xFFFF) as u16
+    let base = (bus.read(addr) as Addr) | ((bus.read((addr + 1) & 0x00FF) as Addr) << 8);
+    index_with_carry(base, registers.get_Y(), is_write, bus)
 }
 
 pub fn fetch_indirect_absolute<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) -> Word {
     let addr = fetch_word(registers, bus);
-    let upper = bus.read((addr & 0xFF00) | (((addr & 0xFF) + 1) & 0xFF) as Addr) as Addr;
-    let addr = (bus.read(addr) as Addr) + (upper << 8) as Addr;
-    addr & 0xFFFF
+    let upper = bus.read((addr & 0xFF00) | (((addr & 0xFF) + 1) & 0xFF)) as Addr;
+    (bus.read(addr) as Addr) | (upper << 8)
+}
+
+// The 65C02 fixed the page wrap bug of JMP ($xxFF), at the cost of a cycle.
+pub fn fetch_indirect_absolute_fixed<T: CpuRegisters, U: CpuBus>(
+    registers: &mut T,
+    bus: &mut U,
+) -> Word {
+    let addr = fetch_word(registers, bus);
+    bus.read(registers.get_PC().wrapping_sub(1));
+    bus.read_word(addr)
+}
+
+pub fn fetch_zeropage_indirect<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) -> Word {
+    let addr = fetch(registers, bus) as Addr;
+    (bus.read(addr) as Addr) | ((bus.read((addr + 1) & 0xFF) as Addr) << 8)
+}
+
+pub fn fetch_absolute_x_indirect<T: CpuRegisters, U: CpuBus>(
+    registers: &mut T,
+    bus: &mut U,
+) -> Word {
+    let base = fetch_word(registers, bus);
+    bus.read(registers.get_PC().wrapping_sub(1));
+    let addr = base.wrapping_add(registers.get_X() as Word);
+    bus.read_word(addr)
+}
+
+// The 6502 adds the index to the low byte first and reads from that address
+// while it fixes up the high byte. Reads skip that cycle when no carry is needed,
+// writes always take it.
+fn index_with_carry<U: CpuBus>(base: Word, index: Data, is_write: bool, bus: &mut U) -> Word {
+    let addr = base.wrapping_add(index as Word);
+    if is_write || (base ^ addr) & 0xFF00 != 0 {
+        bus.read((base & 0xFF00) | (addr & 0x00FF));
+    }
+    addr
 }
@@ -2,16 +2,28 @@ use super::super::bus::cpu_bus::CpuBus;
 use super::super::cpu_registers::CpuRegisters;
 use super::super::helper::*;
 use super::super::types::{Addr, Data, Word};
+use super::Variant;
 
 use std::num::Wrapping;
 
 pub fn process_nmi<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
+    process_interrupt(registers, bus, 0xFFFA);
+}
+
+pub fn process_irq<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
+    process_interrupt(registers, bus, 0xFFFE);
+}
+
+fn process_interrupt<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U, vector: Addr) {
+    // The interrupt sequence replaces an opcode fetch and spends two idle reads.
+    bus.read(registers.get_PC());
+    bus.read(registers.get_PC());
     registers.set_break(false);
     push((registers.get_PC() >> 8) as u8, registers, bus);
     push(registers.get_PC() as u8, registers, bus);
     push_status(registers, bus, false);
     registers.set_interrupt(true);
-    let next = bus.read_word(0xFFFA);
+    let next = bus.read_word(vector);
     registers.set_PC(next);
 }
 
@@ -120,6 +132,7 @@ pub fn plp<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
     // pull flags but keep B
     // registers.set_reserved(true);
     let previous_b = (registers.get_break() as u8) << 4;
+    peek(registers, bus);
     let status = pop(registers, bus) & 0xCF | previous_b;
     registers.set_P(status);
 }
@@ -130,6 +143,7 @@ pub fn pha<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
 }
 
 pub fn pla<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
+    peek(registers, bus);
     let v = pop(registers, bus);
     registers.set_A(v).update_negative_by(v).update_zero_by(v);
 }
@@ -192,6 +206,78 @@ pub fn sbc<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &m
         .set_A(computed as Data);
 }
 
+// Decimal mode ADC, see http://www.6502.org/tutorials/decimal_mode.html
+// The NMOS 6502 takes N and V from the sum before the high digit is adjusted and
+// Z from the binary sum. The 65C02 sets N and Z from the BCD result.
+pub fn adc_decimal<T: CpuRegisters>(fetched: Data, registers: &mut T, variant: Variant) {
+    let acc = registers.get_A();
+    let carry = registers.get_carry() as u16;
+    let mut lower = (acc & 0x0F) as u16 + (fetched & 0x0F) as u16 + carry;
+    if lower >= 0x0A {
+        lower = ((lower + 0x06) & 0x0F) + 0x10;
+    }
+    let mut computed = (acc & 0xF0) as u16 + (fetched & 0xF0) as u16 + lower;
+    let signed = (acc & 0xF0) as i8 as i16 + (fetched & 0xF0) as i8 as i16 + lower as i16;
+    let intermediate = computed as Data;
+    if computed >= 0xA0 {
+        computed += 0x60;
+    }
+    registers
+        .set_overflow(!(-128..=127).contains(&signed))
+        .set_carry(computed >= 0x100)
+        .set_A(computed as Data);
+    if variant == Variant::Cmos65C02 {
+        registers
+            .update_negative_by(computed as Data)
+            .update_zero_by(computed as Data);
+    } else {
+        let binary = acc.wrapping_add(fetched).wrapping_add(carry as Data);
+        registers
+            .update_negative_by(intermediate)
+            .update_zero_by(binary);
+    }
+}
+
+// Decimal mode SBC. C and V always come from the binary difference,
+// N and Z too on the NMOS 6502.
+pub fn sbc_decimal<T: CpuRegisters>(fetched: Data, registers: &mut T, variant: Variant) {
+    let acc = registers.get_A();
+    let borrow = !registers.get_carry() as i16;
+    let binary = acc as i16 - fetched as i16 - borrow;
+    let lower = (acc & 0x0F) as i16 - (fetched & 0x0F) as i16 - borrow;
+    let computed = if variant == Variant::Cmos65C02 {
+        let mut computed = binary;
+        if computed < 0 {
+            computed -= 0x60;
+        }
+        if lower < 0 {
+            computed -= 0x06;
+        }
+        computed
+    } else {
+        let lower = if lower < 0 {
+            ((lower - 0x06) & 0x0F) - 0x10
+        } else {
+            lower
+        };
+        let mut computed = (acc & 0xF0) as i16 - (fetched & 0xF0) as i16 + lower;
+        if computed < 0 {
+            computed -= 0x60;
+        }
+        computed
+    };
+    registers
+        .set_overflow(((acc ^ fetched) & (acc ^ binary as Data) & 0x80) != 0)
+        .set_carry(binary >= 0)
+        .set_A(computed as Data);
+    let flags = if variant == Variant::Cmos65C02 {
+        computed as Data
+    } else {
+        binary as Data
+    };
+    registers.update_negative_by(flags).update_zero_by(flags);
+}
+
 pub fn cpx_imm<T: CpuRegisters>(operand: Word, registers: &mut T) {
     let computed = registers.get_X() as i16 - (operand as i16);
     registers
@@ -303,6 +389,12 @@ pub fn bit<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &m
         .set_overflow((fetched & 0x40) == 0x40);
 }
 
+// BIT #imm of the 65C02 only sets Z.
+pub fn bit_imm<T: CpuRegisters>(operand: Word, registers: &mut T) {
+    let acc = registers.get_A();
+    registers.update_zero_by(operand as Data & acc);
+}
+
 pub fn asl_acc<T: CpuRegisters>(registers: &mut T) {
     let acc = registers.get_A();
     let shifted = (acc << 1) as u8;
@@ -314,7 +406,7 @@ pub fn asl_acc<T: CpuRegisters>(registers: &mut T) {
 }
 
 pub fn asl<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
-    let fetched = bus.read(operand);
+    let fetched = read_modify(operand, bus);
     let shifted = (fetched << 1) as u8;
     registers
         .set_carry(fetched & 0x80 == 0x80)
@@ -334,7 +426,7 @@ pub fn lsr_acc<T: CpuRegisters>(registers: &mut T) {
 }
 
 pub fn lsr<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
-    let fetched = bus.read(operand);
+    let fetched = read_modify(operand, bus);
     let shifted = (fetched >> 1) as u8;
     registers
         .set_carry(fetched & 0x01 == 0x01)
@@ -354,7 +446,7 @@ pub fn rol_acc<T: CpuRegisters>(registers: &mut T) {
 }
 
 pub fn rol<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
-    let fetched = bus.read(operand);
+    let fetched = read_modify(operand, bus);
     let rotated = rotate_to_left(registers, fetched);
     registers
         .set_carry(fetched & 0x80 == 0x80)
@@ -374,7 +466,7 @@ pub fn ror_acc<T: CpuRegisters>(registers: &mut T) {
 }
 
 pub fn ror<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
-    let fetched = bus.read(operand);
+    let fetched = read_modify(operand, bus);
     let rotated = rotate_to_right(registers, fetched);
     registers
         .set_carry(fetched & 0x01 == 0x01)
@@ -394,33 +486,35 @@ pub fn iny<T: CpuRegisters>(registers: &mut T) {
 }
 
 pub fn inc<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
-    let data = bus.read(operand) + 1 as u8;
+    let data = read_modify(operand, bus).wrapping_add(1);
     registers.update_negative_by(data).update_zero_by(data);
     bus.write(operand, data);
 }
 
+pub fn inc_acc<T: CpuRegisters>(registers: &mut T) {
+    let acc = registers.get_A().wrapping_add(1);
+    registers.set_A(acc).update_negative_by(acc).update_zero_by(acc);
+}
+
 pub fn dex<T: CpuRegisters>(registers: &mut T) {
-    let x = registers.get_X() as i8 - 1;
-    registers
-        .set_X(x as Data)
-        .update_negative_by(x as Data)
-        .update_zero_by(x as Data);
+    let x = registers.get_X().wrapping_sub(1);
+    registers.set_X(x).update_negative_by(x).update_zero_by(x);
 }
 
 pub fn dey<T: CpuRegisters>(registers: &mut T) {
-    let y = registers.get_Y() as i8 - 1;
-    registers
-        .set_Y(y as Data)
-        .update_negative_by(y as Data)
-        .update_zero_by(y as Data);
+    let y = registers.get_Y().wrapping_sub(1);
+    registers.set_Y(y).update_negative_by(y).update_zero_by(y);
 }
 
 pub fn dec<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
-    let data = bus.read(operand) as i8 - 1;
-    registers
-        .update_negative_by(data as Data)
-        .update_zero_by(data as Data);
-    bus.write(operand, data as Data);
+    let data = read_modify(operand, bus).wrapping_sub(1);
+    registers.update_negative_by(data).update_zero_by(data);
+    bus.write(operand, data);
+}
+
+pub fn dec_acc<T: CpuRegisters>(registers: &mut T) {
+    let acc = registers.get_A().wrapping_sub(1);
+    registers.set_A(acc).update_negative_by(acc).update_zero_by(acc);
 }
 
 pub fn clc<T: CpuRegisters>(registers: &mut T) {
@@ -444,20 +538,17 @@ pub fn sei<T: CpuRegisters>(registers: &mut T) {
 }
 
 pub fn brk<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
-    let interrupt = registers.get_interrupt();
+    // BRK skips a padding byte, and the I flag does not mask it.
     registers.inc_PC();
     push_pc(registers, bus);
     push_status(registers, bus, true);
     registers.set_interrupt(true);
-    // Ignore interrupt when already set.
-    if !interrupt {
-        let fetched = bus.read_word(0xFFFE);
-        registers.set_PC(fetched);
-    }
-    registers.dec_PC();
+    let fetched = bus.read_word(0xFFFE);
+    registers.set_PC(fetched);
 }
 
 pub fn jsr<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
+    peek(registers, bus);
     let pc = registers.get_PC() - 1;
     push((pc >> 8) as u8, registers, bus);
     push(pc as u8, registers, bus);
@@ -469,61 +560,64 @@ pub fn jmp<T: CpuRegisters>(operand: Word, registers: &mut T) {
 }
 
 pub fn rti<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
+    peek(registers, bus);
     pop_status(registers, bus);
     pop_pc(registers, bus);
     registers.set_reserved(true);
 }
 
 pub fn rts<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
+    peek(registers, bus);
     pop_pc(registers, bus);
+    bus.read(registers.get_PC());
     registers.inc_PC();
 }
 
-pub fn bcc<T: CpuRegisters>(operand: Word, registers: &mut T) {
+pub fn bcc<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
     if !registers.get_carry() {
-        branch(registers, operand);
+        branch(registers, bus, operand);
     }
 }
 
-pub fn bcs<T: CpuRegisters>(operand: Word, registers: &mut T) {
+pub fn bcs<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
     if registers.get_carry() {
-        branch(registers, operand);
+        branch(registers, bus, operand);
     }
 }
 
-pub fn beq<T: CpuRegisters>(operand: Word, registers: &mut T) {
+pub fn beq<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
     if registers.get_zero() {
-        branch(registers, operand);
+        branch(registers, bus, operand);
     }
 }
 
-pub fn bmi<T: CpuRegisters>(operand: Word, registers: &mut T) {
+pub fn bmi<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
     if registers.get_negative() {
-        branch(registers, operand);
+        branch(registers, bus, operand);
     }
 }
 
-pub fn bne<T: CpuRegisters>(operand: Word, registers: &mut T) {
+pub fn bne<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
     if !registers.get_zero() {
-        branch(registers, operand);
+        branch(registers, bus, operand);
     }
 }
 
-pub fn bpl<T: CpuRegisters>(operand: Word, registers: &mut T) {
+pub fn bpl<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
     if !registers.get_negative() {
-        branch(registers, operand);
+        branch(registers, bus, operand);
     }
 }
 
-pub fn bvs<T: CpuRegisters>(operand: Word, registers: &mut T) {
+pub fn bvs<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
     if registers.get_overflow() {
-        branch(registers, operand);
+        branch(registers, bus, operand);
     }
 }
 
-pub fn bvc<T: CpuRegisters>(operand: Word, registers: &mut T) {
+pub fn bvc<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
     if !registers.get_overflow() {
-        branch(registers, operand);
+        branch(registers, bus, operand);
     }
 }
 
@@ -551,20 +645,25 @@ pub fn sax<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &
     bus.write(location, v);
 }
 
-// SAX. Store A 'BitAnd' X
+// DCP. DEC then CMP
 pub fn dcp<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &mut U) {
-    let v = registers.get_A() & registers.get_X();
+    let v = read_modify(location, bus).wrapping_sub(1);
     bus.write(location, v);
+    let computed = (registers.get_A() as i16) - (v as i16);
+    registers
+        .update_negative_by(computed as Data)
+        .update_zero_by(computed as Data)
+        .set_carry(computed >= 0);
 }
 
 pub fn isc<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &mut U) {
     // INC
-    let subtrahend = (Wrapping(bus.read(location)) + Wrapping(1)).0;
+    let subtrahend = (Wrapping(read_modify(location, bus)) + Wrapping(1)).0;
     bus.write(location, subtrahend);
 
     // SBC
     // High carry means "no borrow", thus negate and subtract
-    let subtrahend = bus.read(location) as i16;
+    let subtrahend = subtrahend as i16;
     let diff: i16 = {
         let a = Wrapping(registers.get_A() as i16);
         let v = Wrapping(subtrahend + !registers.get_carry() as i16);
@@ -589,7 +688,7 @@ pub fn isc<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &
 
 pub fn slo<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &mut U) {
     // ASL
-    let mut val = bus.read(location);
+    let mut val = read_modify(location, bus);
     registers.set_carry(val & 0x80 != 0);
     val <<= 1;
     bus.write(location, val);
@@ -601,7 +700,7 @@ pub fn slo<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &
 pub fn rla<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &mut U) {
     // ROL
     let prev_c = registers.get_carry();
-    let mut operand = bus.read(location);
+    let mut operand = read_modify(location, bus);
     registers.set_carry(operand & 0x80 != 0);
     operand <<= 1;
     operand |= prev_c as u8;
@@ -613,7 +712,7 @@ pub fn rla<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &
 
 pub fn sre<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &mut U) {
     // LSR
-    let mut operand = bus.read(location);
+    let mut operand = read_modify(location, bus);
     registers.set_carry((operand & 0x01) != 0);
     operand >>= 1;
     bus.write(location, operand);
@@ -625,7 +724,7 @@ pub fn sre<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &
 pub fn rra<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &mut U) {
     // ROR
     let prev_c = registers.get_carry();
-    let mut operand: u16 = bus.read(location) as u16;
+    let mut operand: u16 = read_modify(location, bus) as u16;
     registers.set_carry(operand & 0x01 != 0);
     operand = (operand >> 1) | ((prev_c as u16) << 7);
     bus.write(location, (operand & 0xFF) as u8);
@@ -644,6 +743,54 @@ pub fn rra<T: CpuRegisters, U: CpuBus>(location: Word, registers: &mut T, bus: &
         .update_negative_by(sum);
 }
 
+// 65C02 instructions.
+
+pub fn bra<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
+    branch(registers, bus, operand);
+}
+
+pub fn phx<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
+    let x = registers.get_X();
+    push(x, registers, bus);
+}
+
+pub fn phy<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
+    let y = registers.get_Y();
+    push(y, registers, bus);
+}
+
+pub fn plx<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
+    peek(registers, bus);
+    let v = pop(registers, bus);
+    registers.set_X(v).update_negative_by(v).update_zero_by(v);
+}
+
+pub fn ply<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
+    peek(registers, bus);
+    let v = pop(registers, bus);
+    registers.set_Y(v).update_negative_by(v).update_zero_by(v);
+}
+
+pub fn stz<T: CpuRegisters, U: CpuBus>(operand: Word, _registers: &mut T, bus: &mut U) {
+    bus.write(operand, 0x00);
+}
+
+// Test and reset bits: Z is set from A & M, then the bits set in A are cleared in M.
+pub fn trb<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
+    let fetched = read_modify(operand, bus);
+    let acc = registers.get_A();
+    registers.update_zero_by(fetched & acc);
+    bus.write(operand, fetched & !acc);
+}
+
+// Test and set bits: like TRB, but the bits set in A are set in M.
+pub fn tsb<T: CpuRegisters, U: CpuBus>(operand: Word, registers: &mut T, bus: &mut U) {
+    let fetched = read_modify(operand, bus);
+    let acc = registers.get_A();
+    registers.update_zero_by(fetched & acc);
+    bus.write(operand, fetched | acc);
+}
+
 fn rotate_to_right<T: CpuRegisters>(registers: &mut T, v: Data) -> Data {
     ((v >> 1) as Data | if registers.get_carry() { 0x80 } else { 0x00 }) as Data
 }
@@ -652,6 +799,20 @@ fn rotate_to_left<T: CpuRegisters>(registers: &mut T, v: Data) -> Data {
     ((v << 1) as Data | if registers.get_carry() { 0x01 } else { 0x00 }) as Data
 }
 
+// Read-modify-write instructions write the unmodified value back
+// while they compute the result.
+fn read_modify<U: CpuBus>(addr: Addr, bus: &mut U) -> Data {
+    let data = bus.read(addr);
+    bus.write(addr, data);
+    data
+}
+
+// Stack instructions spend an internal cycle reading the top of the stack.
+fn peek<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
+    let sp = registers.get_SP() as Addr;
+    bus.read(0x0100 | sp);
+}
+
 fn push<T: CpuRegisters, U: CpuBus>(data: Data, registers: &mut T, bus: &mut U) {
     let addr = registers.get_SP() as Addr;
     bus.write(addr | 0x0100, data);
@@ -659,7 +820,8 @@ fn push<T: CpuRegisters, U: CpuBus>(data: Data, registers: &mut T, bus: &mut U)
 }
 
 fn push_status<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U, with_b_flag: bool) {
-    let status = registers.get_P() | if with_b_flag { 1 << 4 } else { 0 };
+    // Bit 5 is not a real flag and is always pushed as 1.
+    let status = registers.get_P() | 1 << 5 | if with_b_flag { 1 << 4 } else { 0 };
     push(status, registers, bus);
 }
 
@@ -685,7 +847,14 @@ fn push_pc<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
     push(pc as u8, registers, bus);
 }
 
-fn branch<T: CpuRegisters>(registers: &mut T, addr: Addr) {
+fn branch<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U, addr: Addr) {
+    // A taken branch spends a cycle adding the offset, and one more when the
+    // high byte of PC has to be fixed up.
+    let pc = registers.get_PC();
+    bus.read(pc);
+    if (pc ^ addr) & 0xFF00 != 0 {
+        bus.read((pc & 0xFF00) | (addr & 0x00FF));
+    }
     registers.set_PC(addr);
 }
 
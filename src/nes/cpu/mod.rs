@@ -9,113 +9,375 @@ use std::fmt::Debug;
 
 use super::bus::cpu_bus::CpuBus;
 use super::cpu_registers::CpuRegisters;
-use super::types::{Data, Addr};
+use super::types::{Addr, Data, Word};
 
-pub fn reset_with_addr<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U, addr: Addr) {
+pub fn reset_with_addr<T: CpuRegisters>(registers: &mut T, addr: Addr) {
     registers.reset(addr);
 }
 
-pub fn reset<T: CpuRegisters, U: CpuBus>(registers: &mut T, bus: &mut U) {
-    const RESET_VECTOR: Addr = 0xFFFC;
-    let addr = bus.read_word(RESET_VECTOR);
-    reset_with_addr(registers, bus, addr);
+// CPU cores sharing the 6502 instruction set.
+// The NES uses the Ricoh 2A03, an NMOS 6502 whose decimal mode is disconnected.
+// The other variants are for running the core outside of the NES.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    Ricoh2A03,
+    // ADC and SBC honour the D flag, with the NMOS flag quirks.
+    Nmos6502,
+    // CMOS 65C02: new instructions, valid N and Z in decimal mode,
+    // D cleared by interrupts and no JMP ($xxFF) bug.
+    Cmos65C02,
 }
 
-pub fn step<T: CpuRegisters + Debug, U: CpuBus>(
-    registers: &mut T,
-    bus: &mut U,
-    nmi: &mut bool,
-) -> Data {
-    if *nmi {
-        process_nmi(registers, bus);
-        *nmi = false;
-    }
-    let code = fetch(registers, bus);
-    // println!("code: {:X}", code);
-    let ref map = opecode::MAP;
-    let code = &*map.get(&code).unwrap();
-    let operand = fetch_operand(&code, registers, bus);
-    // println!("opecode = {}, {:?} pc = {:x}, operand = {:x}", &_code, code.name, &registers.get_PC(), operand);
-    match code.name {
-        Instruction::LDA if code.mode == Addressing::Immediate => lda_imm(operand, registers),
-        Instruction::LDA => lda(operand, registers, bus),
-        Instruction::LDX if code.mode == Addressing::Immediate => ldx_imm(operand, registers),
-        Instruction::LDX => ldx(operand, registers, bus),
-        Instruction::LDY if code.mode == Addressing::Immediate => ldy_imm(operand, registers),
-        Instruction::LDY => ldy(operand, registers, bus),
-        Instruction::STA => sta(operand, registers, bus),
-        Instruction::STX => stx(operand, registers, bus),
-        Instruction::STY => sty(operand, registers, bus),
-        Instruction::TXA => txa(registers),
-        Instruction::TYA => tya(registers),
-        Instruction::TXS => txs(registers),
-        Instruction::TAY => tay(registers),
-        Instruction::TAX => tax(registers),
-        Instruction::TSX => tsx(registers),
-        Instruction::PHP => php(registers, bus),
-        Instruction::PLP => plp(registers, bus),
-        Instruction::PHA => pha(registers, bus),
-        Instruction::PLA => pla(registers, bus),
-        Instruction::ADC if code.mode == Addressing::Immediate => adc_imm(operand, registers),
-        Instruction::ADC => adc(operand, registers, bus),
-        Instruction::SBC if code.mode == Addressing::Immediate => sbc_imm(operand, registers),
-        Instruction::SBC => sbc(operand, registers, bus),
-        Instruction::CPX if code.mode == Addressing::Immediate => cpx_imm(operand, registers),
-        Instruction::CPX => cpx(operand, registers, bus),
-        Instruction::CPY if code.mode == Addressing::Immediate => cpy_imm(operand, registers),
-        Instruction::CPY => cpy(operand, registers, bus),
-        Instruction::CMP if code.mode == Addressing::Immediate => cmp_imm(operand, registers),
-        Instruction::CMP => cmp(operand, registers, bus),
-        Instruction::AND if code.mode == Addressing::Immediate => and_imm(operand, registers),
-        Instruction::AND => and(operand, registers, bus),
-        Instruction::EOR if code.mode == Addressing::Immediate => eor_imm(operand, registers),
-        Instruction::EOR => eor(operand, registers, bus),
-        Instruction::ORA if code.mode == Addressing::Immediate => ora_imm(operand, registers),
-        Instruction::ORA => ora(operand, registers, bus),
-        Instruction::BIT => bit(operand, registers, bus),
-        Instruction::ASL if code.mode == Addressing::Accumulator => asl_acc(registers),
-        Instruction::ASL => asl(operand, registers, bus),
-        Instruction::LSR if code.mode == Addressing::Accumulator => lsr_acc(registers),
-        Instruction::LSR => lsr(operand, registers, bus),
-        Instruction::ROL if code.mode == Addressing::Accumulator => rol_acc(registers),
-        Instruction::ROL => rol(operand, registers, bus),
-        Instruction::ROR if code.mode == Addressing::Accumulator => ror_acc(registers),
-        Instruction::ROR => ror(operand, registers, bus),
-        Instruction::INX => inx(registers),
-        Instruction::INY => iny(registers),
-        Instruction::INC => inc(operand, registers, bus),
-        Instruction::DEX => dex(registers),
-        Instruction::DEY => dey(registers),
-        Instruction::DEC => dec(operand, registers, bus),
-        Instruction::CLC => clc(registers),
-        Instruction::CLI => cli(registers),
-        Instruction::CLV => clv(registers),
-        Instruction::SEC => sec(registers),
-        Instruction::SEI => sei(registers),
-        Instruction::NOP => (),
-        Instruction::BRK => brk(registers, bus),
-        Instruction::JSR => jsr(operand, registers, bus),
-        Instruction::JMP => jmp(operand, registers),
-        Instruction::RTI => rti(registers, bus),
-        Instruction::RTS => rts(registers, bus),
-        Instruction::BCC => bcc(operand, registers),
-        Instruction::BPL => bpl(operand, registers),
-        Instruction::BMI => bmi(operand, registers),
-        Instruction::BVC => bvc(operand, registers),
-        Instruction::BVS => bvs(operand, registers),
-        Instruction::BCS => bcs(operand, registers),
-        Instruction::BNE => bne(operand, registers),
-        Instruction::BEQ => beq(operand, registers),
-        Instruction::SED => sed(registers),
-        Instruction::CLD => cld(registers),
-        Instruction::LAX => lax(operand, registers, bus),
-        Instruction::SAX => sax(operand, registers, bus),
-        Instruction::DCP => dcp(operand, registers,bus),
-        Instruction::ISC => isc(operand, registers, bus),
-        Instruction::SLO => slo(operand, registers, bus),
-        Instruction::RLA => rla(operand, registers, bus),
-        Instruction::SRE => sre(operand, registers, bus),
-        Instruction::RRA => rra(operand, registers, bus),
-    }
-    code.cycle
+impl Variant {
+    fn opecodes(self) -> &'static [Opecode; 256] {
+        match self {
+            Variant::Ricoh2A03 | Variant::Nmos6502 => &OPECODES,
+            Variant::Cmos65C02 => &CMOS_OPECODES,
+        }
+    }
+
+    fn has_decimal_mode(self) -> bool {
+        self != Variant::Ricoh2A03
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Cpu {
+    variant: Variant,
+}
+
+impl Cpu {
+    pub fn new(variant: Variant) -> Self {
+        Cpu { variant }
+    }
+
+    pub fn get_variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn reset<T: CpuRegisters, U: CpuBus>(&self, registers: &mut T, bus: &mut U) {
+        const RESET_VECTOR: Addr = 0xFFFC;
+        // Reset runs the interrupt sequence without writing to the stack,
+        // so it takes 7 cycles like the other interrupts.
+        for _ in 0..5 {
+            bus.read(registers.get_PC());
+        }
+        let addr = bus.read_word(RESET_VECTOR);
+        reset_with_addr(registers, addr);
+    }
+
+    // The reset button runs the same sequence on a running CPU. Only SP and the
+    // I flag change, the stack is read instead of written.
+    pub fn soft_reset<T: CpuRegisters, U: CpuBus>(&self, registers: &mut T, bus: &mut U) {
+        const RESET_VECTOR: Addr = 0xFFFC;
+        bus.read(registers.get_PC());
+        bus.read(registers.get_PC());
+        for _ in 0..3 {
+            bus.read(0x0100 | registers.get_SP() as Addr);
+            registers.dec_SP();
+        }
+        registers.set_interrupt(true);
+        self.clear_decimal(registers);
+        let addr = bus.read_word(RESET_VECTOR);
+        registers.set_PC(addr);
+    }
+
+    pub fn step<T: CpuRegisters + Debug, U: CpuBus>(&self, registers: &mut T, bus: &mut U) {
+        if bus.take_nmi() {
+            process_nmi(registers, bus);
+            self.clear_decimal(registers);
+        } else if bus.is_irq() && !registers.get_interrupt() {
+            process_irq(registers, bus);
+            self.clear_decimal(registers);
+        }
+        let code = &self.variant.opecodes()[fetch(registers, bus) as usize];
+        let operand = fetch_operand(code, self.variant, registers, bus);
+        let decimal = self.variant.has_decimal_mode() && registers.get_decimal();
+        match (code.name, code.mode) {
+            (Instruction::LDA, Addressing::Immediate) => lda_imm(operand, registers),
+            (Instruction::LDA, _) => lda(operand, registers, bus),
+            (Instruction::LDX, Addressing::Immediate) => ldx_imm(operand, registers),
+            (Instruction::LDX, _) => ldx(operand, registers, bus),
+            (Instruction::LDY, Addressing::Immediate) => ldy_imm(operand, registers),
+            (Instruction::LDY, _) => ldy(operand, registers, bus),
+            (Instruction::STA, _) => sta(operand, registers, bus),
+            (Instruction::STX, _) => stx(operand, registers, bus),
+            (Instruction::STY, _) => sty(operand, registers, bus),
+            (Instruction::TXA, _) => txa(registers),
+            (Instruction::TYA, _) => tya(registers),
+            (Instruction::TXS, _) => txs(registers),
+            (Instruction::TAY, _) => tay(registers),
+            (Instruction::TAX, _) => tax(registers),
+            (Instruction::TSX, _) => tsx(registers),
+            (Instruction::PHP, _) => php(registers, bus),
+            (Instruction::PLP, _) => plp(registers, bus),
+            (Instruction::PHA, _) => pha(registers, bus),
+            (Instruction::PLA, _) => pla(registers, bus),
+            (Instruction::ADC, _) if decimal => {
+                let fetched = self.read_decimal_operand(code, operand, registers, bus);
+                adc_decimal(fetched, registers, self.variant);
+            }
+            (Instruction::ADC, Addressing::Immediate) => adc_imm(operand, registers),
+            (Instruction::ADC, _) => adc(operand, registers, bus),
+            (Instruction::SBC, _) if decimal => {
+                let fetched = self.read_decimal_operand(code, operand, registers, bus);
+                sbc_decimal(fetched, registers, self.variant);
+            }
+            (Instruction::SBC, Addressing::Immediate) => sbc_imm(operand, registers),
+            (Instruction::SBC, _) => sbc(operand, registers, bus),
+            (Instruction::CPX, Addressing::Immediate) => cpx_imm(operand, registers),
+            (Instruction::CPX, _) => cpx(operand, registers, bus),
+            (Instruction::CPY, Addressing::Immediate) => cpy_imm(operand, registers),
+            (Instruction::CPY, _) => cpy(operand, registers, bus),
+            (Instruction::CMP, Addressing::Immediate) => cmp_imm(operand, registers),
+            (Instruction::CMP, _) => cmp(operand, registers, bus),
+            (Instruction::AND, Addressing::Immediate) => and_imm(operand, registers),
+            (Instruction::AND, _) => and(operand, registers, bus),
+            (Instruction::EOR, Addressing::Immediate) => eor_imm(operand, registers),
+            (Instruction::EOR, _) => eor(operand, registers, bus),
+            (Instruction::ORA, Addressing::Immediate) => ora_imm(operand, registers),
+            (Instruction::ORA, _) => ora(operand, registers, bus),
+            (Instruction::BIT, Addressing::Immediate) => bit_imm(operand, registers),
+            (Instruction::BIT, _) => bit(operand, registers, bus),
+            (Instruction::ASL, Addressing::Accumulator) => asl_acc(registers),
+            (Instruction::ASL, _) => asl(operand, registers, bus),
+            (Instruction::LSR, Addressing::Accumulator) => lsr_acc(registers),
+            (Instruction::LSR, _) => lsr(operand, registers, bus),
+            (Instruction::ROL, Addressing::Accumulator) => rol_acc(registers),
+            (Instruction::ROL, _) => rol(operand, registers, bus),
+            (Instruction::ROR, Addressing::Accumulator) => ror_acc(registers),
+            (Instruction::ROR, _) => ror(operand, registers, bus),
+            (Instruction::INX, _) => inx(registers),
+            (Instruction::INY, _) => iny(registers),
+            (Instruction::INC, Addressing::Accumulator) => inc_acc(registers),
+            (Instruction::INC, _) => inc(operand, registers, bus),
+            (Instruction::DEX, _) => dex(registers),
+            (Instruction::DEY, _) => dey(registers),
+            (Instruction::DEC, Addressing::Accumulator) => dec_acc(registers),
+            (Instruction::DEC, _) => dec(operand, registers, bus),
+            (Instruction::CLC, _) => clc(registers),
+            (Instruction::CLI, _) => cli(registers),
+            (Instruction::CLV, _) => clv(registers),
+            (Instruction::SEC, _) => sec(registers),
+            (Instruction::SEI, _) => sei(registers),
+            (Instruction::NOP, Addressing::Implied) | (Instruction::NOP, Addressing::Immediate) => (),
+            (Instruction::NOP, _) => {
+                bus.read(operand);
+            }
+            (Instruction::BRK, _) => {
+                brk(registers, bus);
+                self.clear_decimal(registers);
+            }
+            (Instruction::JSR, _) => jsr(operand, registers, bus),
+            (Instruction::JMP, _) => jmp(operand, registers),
+            (Instruction::RTI, _) => rti(registers, bus),
+            (Instruction::RTS, _) => rts(registers, bus),
+            (Instruction::BCC, _) => bcc(operand, registers, bus),
+            (Instruction::BPL, _) => bpl(operand, registers, bus),
+            (Instruction::BMI, _) => bmi(operand, registers, bus),
+            (Instruction::BVC, _) => bvc(operand, registers, bus),
+            (Instruction::BVS, _) => bvs(operand, registers, bus),
+            (Instruction::BCS, _) => bcs(operand, registers, bus),
+            (Instruction::BNE, _) => bne(operand, registers, bus),
+            (Instruction::BEQ, _) => beq(operand, registers, bus),
+            (Instruction::SED, _) => sed(registers),
+            (Instruction::CLD, _) => cld(registers),
+            (Instruction::LAX, _) => lax(operand, registers, bus),
+            (Instruction::SAX, _) => sax(operand, registers, bus),
+            (Instruction::DCP, _) => dcp(operand, registers, bus),
+            // Unofficial opcodes always do binary arithmetic, even on the NMOS 6502.
+            (Instruction::ISC, _) => isc(operand, registers, bus),
+            (Instruction::SLO, _) => slo(operand, registers, bus),
+            (Instruction::RLA, _) => rla(operand, registers, bus),
+            (Instruction::SRE, _) => sre(operand, registers, bus),
+            (Instruction::RRA, _) => rra(operand, registers, bus),
+            (Instruction::BRA, _) => bra(operand, registers, bus),
+            (Instruction::PHX, _) => phx(registers, bus),
+            (Instruction::PHY, _) => phy(registers, bus),
+            (Instruction::PLX, _) => plx(registers, bus),
+            (Instruction::PLY, _) => ply(registers, bus),
+            (Instruction::STZ, _) => stz(operand, registers, bus),
+            (Instruction::TRB, _) => trb(operand, registers, bus),
+            (Instruction::TSB, _) => tsb(operand, registers, bus),
+        }
+    }
+
+    // The 65C02 clears D when it takes an interrupt.
+    fn clear_decimal<T: CpuRegisters>(&self, registers: &mut T) {
+        if self.variant == Variant::Cmos65C02 {
+            registers.set_decimal(false);
+        }
+    }
+
+    // The 65C02 spends one more cycle on decimal ADC and SBC to fix up the flags.
+    fn read_decimal_operand<T: CpuRegisters, U: CpuBus>(
+        &self,
+        code: &Opecode,
+        operand: Word,
+        registers: &mut T,
+        bus: &mut U,
+    ) -> Data {
+        let fetched = if code.mode == Addressing::Immediate {
+            operand as Data
+        } else {
+            bus.read(operand)
+        };
+        if self.variant == Variant::Cmos65C02 {
+            bus.read(registers.get_PC().wrapping_sub(1));
+        }
+        fetched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::cpu_registers::Registers;
+    use super::*;
+
+    struct FlatBus {
+        mem: Vec<Data>,
+        irq: bool,
+    }
+
+    impl CpuBus for FlatBus {
+        fn read(&mut self, addr: Addr) -> Data {
+            self.mem[addr as usize]
+        }
+        fn read_word(&mut self, addr: Addr) -> Word {
+            let lower = self.read(addr) as Word;
+            let upper = self.read(addr.wrapping_add(1)) as Word;
+            upper << 8 | lower
+        }
+        fn write(&mut self, addr: Addr, data: Data) {
+            self.mem[addr as usize] = data;
+        }
+        fn is_irq(&mut self) -> bool {
+            self.irq
+        }
+    }
+
+    // Runs `steps` instructions of `program` loaded at $0200.
+    fn run(variant: Variant, program: &[Data], steps: usize) -> (Registers, FlatBus) {
+        let mut bus = FlatBus {
+            mem: vec![0; 0x10000],
+            irq: false,
+        };
+        bus.mem[0x0200..0x0200 + program.len()].copy_from_slice(program);
+        let mut registers = Registers::new();
+        reset_with_addr(&mut registers, 0x0200);
+        let cpu = Cpu::new(variant);
+        for _ in 0..steps {
+            cpu.step(&mut registers, &mut bus);
+        }
+        (registers, bus)
+    }
+
+    #[test]
+    fn test_decimal_adc() {
+        // SED; CLC; LDA #$09; ADC #$01
+        let program = [0xF8, 0x18, 0xA9, 0x09, 0x69, 0x01];
+        let (registers, _) = run(Variant::Ricoh2A03, &program, 4);
+        assert_eq!(registers.get_A(), 0x0A);
+        let (registers, _) = run(Variant::Nmos6502, &program, 4);
+        assert_eq!(registers.get_A(), 0x10);
+        let (registers, _) = run(Variant::Cmos65C02, &program, 4);
+        assert_eq!(registers.get_A(), 0x10);
+    }
+
+    #[test]
+    fn test_decimal_adc_flags() {
+        // SED; CLC; LDA #$99; ADC #$01
+        let program = [0xF8, 0x18, 0xA9, 0x99, 0x69, 0x01];
+        let (registers, _) = run(Variant::Nmos6502, &program, 4);
+        assert_eq!(registers.get_A(), 0x00);
+        assert!(registers.get_carry());
+        // N comes from the unadjusted sum and Z from the binary sum ($9A).
+        assert!(registers.get_negative());
+        assert!(!registers.get_zero());
+        let (registers, _) = run(Variant::Cmos65C02, &program, 4);
+        assert_eq!(registers.get_A(), 0x00);
+        assert!(registers.get_carry());
+        assert!(!registers.get_negative());
+        assert!(registers.get_zero());
+    }
+
+    #[test]
+    fn test_decimal_sbc() {
+        // SED; SEC; LDA #$00; SBC #$01
+        let program = [0xF8, 0x38, 0xA9, 0x00, 0xE9, 0x01];
+        for &variant in &[Variant::Nmos6502, Variant::Cmos65C02] {
+            let (registers, _) = run(variant, &program, 4);
+            assert_eq!(registers.get_A(), 0x99);
+            assert!(!registers.get_carry());
+        }
+        // SED; SEC; LDA #$42; SBC #$13
+        let program = [0xF8, 0x38, 0xA9, 0x42, 0xE9, 0x13];
+        let (registers, _) = run(Variant::Nmos6502, &program, 4);
+        assert_eq!(registers.get_A(), 0x29);
+        let (registers, _) = run(Variant::Ricoh2A03, &program, 4);
+        assert_eq!(registers.get_A(), 0x2F);
+    }
+
+    #[test]
+    fn test_cmos_instructions() {
+        let program = [
+            0xA9, 0x0F, // LDA #$0F
+            0x1A, // INC A
+            0x85, 0x10, // STA $10
+            0x64, 0x10, // STZ $10
+            0xA9, 0x81, // LDA #$81
+            0x04, 0x10, // TSB $10
+            0xA2, 0x33, // LDX #$33
+            0xDA, // PHX
+            0x7A, // PLY
+            0x80, 0x02, // BRA +2
+            0xA0, 0x00, // LDY #$00 (skipped)
+            0xB2, 0x20, // LDA ($20)
+        ];
+        let (registers, bus) = run(Variant::Cmos65C02, &program, 11);
+        assert_eq!(bus.mem[0x10], 0x81);
+        assert_eq!(registers.get_Y(), 0x33);
+        assert_eq!(registers.get_PC(), 0x0215);
+        assert_eq!(registers.get_A(), 0x00);
+    }
+
+    #[test]
+    fn test_jmp_indirect_page_wrap() {
+        // JMP ($02FF)
+        let mut program = vec![0x6C, 0xFF, 0x02];
+        program.resize(0x101, 0x00);
+        program[0xFF] = 0x34;
+        program[0x100] = 0x12;
+        program[0x00] = 0x6C;
+        // The NMOS part reads the high byte from $0200, the opcode itself.
+        let (registers, _) = run(Variant::Ricoh2A03, &program, 1);
+        assert_eq!(registers.get_PC(), 0x6C34);
+        let (registers, _) = run(Variant::Cmos65C02, &program, 1);
+        assert_eq!(registers.get_PC(), 0x1234);
+    }
+
+    #[test]
+    fn test_irq() {
+        let mut bus = FlatBus {
+            mem: vec![0xEA; 0x10000],
+            irq: true,
+        };
+        bus.mem[0xFFFE] = 0x00;
+        bus.mem[0xFFFF] = 0x03;
+        let mut registers = Registers::new();
+        reset_with_addr(&mut registers, 0x0200);
+        let cpu = Cpu::new(Variant::Ricoh2A03);
+        // Masked by the I flag, set after reset.
+        cpu.step(&mut registers, &mut bus);
+        assert_eq!(registers.get_PC(), 0x0201);
+        registers.set_interrupt(false);
+        cpu.step(&mut registers, &mut bus);
+        // The NOP at the vector has run too.
+        assert_eq!(registers.get_PC(), 0x0301);
+        assert!(registers.get_interrupt());
+        // Return address, then P with B clear.
+        assert_eq!(bus.mem[0x01FD], 0x02);
+        assert_eq!(bus.mem[0x01FC], 0x01);
+        assert_eq!(bus.mem[0x01FB] & 0x10, 0x00);
+    }
 }
@@ -1,7 +1,8 @@
-use super::types::Data;
-use nes::ppu::Ppu;
-use nes::ram::Ram;
+use super::types::{Addr, Data};
 
+// OAM DMA ($4014).
+// The copy itself is done by the CPU bus, which halts the CPU and moves the page
+// through $2004 one get/put cycle pair per byte.
 #[derive(Debug)]
 pub struct Dma {
     register: Data,
@@ -21,15 +22,17 @@ impl Dma {
         self.should_run = true;
     }
 
+    pub fn reset(&mut self) {
+        self.should_run = false;
+    }
+
     pub fn should_run(&self) -> bool {
         self.should_run
     }
 
-    pub fn run(&mut self, ram: &Ram, ppu: &mut Ppu) {
-        let addr = (self.register as u16) << 8;
-        for i in 0..0x100 {
-            ppu.transfer_sprite(i, ram.read(addr + i));
-        }
+    // Returns the source address of the page and clears the request.
+    pub fn start(&mut self) -> Addr {
         self.should_run = false;
+        (self.register as Addr) << 8
     }
 }
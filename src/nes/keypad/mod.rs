@@ -1,41 +1,117 @@
-use super::types::{Addr, Data};
+use super::types::Data;
 
+// Standard controller, an 8 bit shift register of the buttons in the order
+// A, B, Select, Start, Up, Down, Left, Right.
 #[derive(Debug)]
-pub struct Keypad {
-    addr: Addr,
-    reset: bool,
+struct Controller {
+    // Buttons currently held.
+    buttons: u8,
     register: u8,
-    buffer: u8,
+}
+
+impl Controller {
+    fn new() -> Self {
+        Controller {
+            buttons: 0,
+            register: 0,
+        }
+    }
+
+    fn reload(&mut self) {
+        self.register = self.buttons;
+    }
+
+    // The serial input is tied high, so after the 8 buttons it reads 1.
+    fn read(&mut self, is_strobe: bool) -> u8 {
+        if is_strobe {
+            self.reload();
+        }
+        let ret = self.register & 0x01;
+        self.register = (self.register >> 1) | 0x80;
+        ret
+    }
+}
+
+// Both controller ports. A write to $4016 strobes the two at once, they are
+// read from $4016 and $4017.
+#[derive(Debug)]
+pub struct Keypad {
+    // While the strobe is high the buttons are reloaded continuously,
+    // so reads keep returning A.
+    is_strobe: bool,
+    controllers: [Controller; 2],
 }
 
 impl Keypad {
     pub fn new() -> Self {
         Keypad {
-            addr: 0,
-            reset: false,
-            register: 0,
-            buffer: 0,
+            is_strobe: false,
+            controllers: [Controller::new(), Controller::new()],
         }
     }
 
-    pub fn update(&mut self, data: Data) {
-        self.buffer = data;
+    pub fn update(&mut self, pad1: Data, pad2: Data) {
+        self.controllers[0].buttons = pad1;
+        self.controllers[1].buttons = pad2;
+        if self.is_strobe {
+            self.controllers.iter_mut().for_each(|c| c.reload());
+        }
     }
 
     pub fn write(&mut self, data: Data) {
-        if data & 0x01 == 0x01 {
-            self.reset = true;
-        } else if self.reset && data & 0x00 == 0x00 {
-            self.reset = false;
-            self.addr = 0;
-            self.register = self.buffer;
+        self.is_strobe = data & 0x01 == 0x01;
+        if self.is_strobe {
+            self.controllers.iter_mut().for_each(|c| c.reload());
         }
     }
 
-    pub fn read(&mut self) -> u8 {
-        let v = (0x01 << self.addr) as u8;
-        let ret = ((self.register & v) >> self.addr) as u8;
-        self.addr += 1;
-        ret
+    // `port` is 0 for $4016 and 1 for $4017.
+    pub fn read(&mut self, port: usize) -> u8 {
+        self.controllers[port].read(self.is_strobe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(keypad: &mut Keypad, port: usize) -> Vec<u8> {
+        (0..10).map(|_| keypad.read(port)).collect()
+    }
+
+    #[test]
+    fn read_buttons_then_ones() {
+        let mut keypad = Keypad::new();
+        // A and Right on port 1, B and Start on port 2.
+        keypad.update(0x81, 0x0A);
+        keypad.write(0x01);
+        keypad.write(0x00);
+        assert_eq!(read_all(&mut keypad, 0), vec![1, 0, 0, 0, 0, 0, 0, 1, 1, 1]);
+        assert_eq!(read_all(&mut keypad, 1), vec![0, 1, 0, 1, 0, 0, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn strobe_high_reloads() {
+        let mut keypad = Keypad::new();
+        keypad.update(0x01, 0x00);
+        keypad.write(0x01);
+        // The register keeps being reloaded, so A is read every time.
+        assert_eq!(read_all(&mut keypad, 0), vec![1; 10]);
+        // Buttons changed while the strobe is high are picked up.
+        keypad.update(0x00, 0x02);
+        assert_eq!(keypad.read(0), 0);
+        keypad.write(0x00);
+        assert_eq!(read_all(&mut keypad, 1), vec![0, 1, 0, 0, 0, 0, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn no_reload_without_strobe() {
+        let mut keypad = Keypad::new();
+        keypad.update(0x01, 0x00);
+        keypad.write(0x01);
+        keypad.write(0x00);
+        // Pressing buttons after the strobe does not change the latched state.
+        keypad.update(0xFF, 0xFF);
+        assert_eq!(read_all(&mut keypad, 0), vec![1, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
     }
 }
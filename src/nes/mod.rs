@@ -12,17 +12,28 @@ mod mmc;
 mod parser;
 mod ppu;
 mod ram;
+mod recorder;
+mod region;
 mod renderer;
 mod rom;
 mod types;
 
+pub use self::apu::{Apu, Channel};
+// Returned by `Apu::channel_state`, for library users only.
+#[allow(unused_imports)]
+pub use self::apu::ChannelState;
+pub use self::bus::cpu_bus::CpuBus;
+pub use self::cpu::{Cpu, Variant};
+pub use self::cpu_registers::{CpuRegisters, Registers};
 pub use self::keypad::*;
-pub use self::ppu::background;
-pub use self::ppu::Tile;
-pub use self::ppu::{Sprite, SpritePosition, SpriteWithCtx};
+pub use self::ram::RamInit;
+pub use self::recorder::VgmLogger;
+// Used by frontends to save `take_audio_samples`.
+#[allow(unused_imports)]
+pub use self::recorder::WavWriter;
+pub use self::region::Region;
 pub use self::renderer::*;
 
-use self::apu::*;
 use self::bus::cpu_bus;
 use self::dma::*;
 use self::mmc::*;
@@ -31,20 +42,20 @@ use self::ram::Ram;
 use self::rom::Rom;
 use self::types::{Addr, Data};
 
-const DMA_CYCLES: u16 = 514;
-
 #[derive(Debug)]
 pub struct Context {
     ppu: Ppu,
     program_rom: Rom,
     work_ram: Ram,
-    cpu_registers: cpu_registers::Registers,
+    cpu: Cpu,
+    cpu_registers: Registers,
     keypad: Keypad,
     dma: Dma,
     apu: Apu,
-    nmi: bool,
     renderer: Renderer,
     mmc: Mmc,
+    cycles: u64,
+    open_bus: Data,
 }
 
 pub fn reset(ctx: &mut Context) {
@@ -56,30 +67,22 @@ pub fn reset(ctx: &mut Context) {
         &mut ctx.keypad,
         &mut ctx.dma,
         &mut ctx.mmc,
+        &mut ctx.cycles,
+        &mut ctx.open_bus,
     );
-    cpu::reset(&mut ctx.cpu_registers, &mut cpu_bus);
+    ctx.cpu.reset(&mut ctx.cpu_registers, &mut cpu_bus);
 }
 
 fn reset_with_addr(ctx: &mut Context, addr: Addr) {
-    let mut cpu_bus = cpu_bus::Bus::new(
-        &ctx.program_rom,
-        &mut ctx.work_ram,
-        &mut ctx.ppu,
-        &mut ctx.apu,
-        &mut ctx.keypad,
-        &mut ctx.dma,
-        &mut ctx.mmc,
-    );
-    cpu::reset_with_addr(&mut ctx.cpu_registers, &mut cpu_bus, addr);
+    cpu::reset_with_addr(&mut ctx.cpu_registers, addr);
 }
 
-pub fn run(ctx: &mut Context, key_state: u8) {
-    ctx.keypad.update(key_state);
+// `pad1` and `pad2` are the buttons held on each controller, bit 0 is A
+// and bit 7 is Right.
+pub fn run(ctx: &mut Context, pad1: u8, pad2: u8) {
+    ctx.keypad.update(pad1, pad2);
     loop {
-        let cycle: u16 = if ctx.dma.should_run() {
-            ctx.dma.run(&ctx.work_ram, &mut ctx.ppu);
-            DMA_CYCLES
-        } else {
+        let is_ready = {
             let mut cpu_bus = cpu_bus::Bus::new(
                 &ctx.program_rom,
                 &mut ctx.work_ram,
@@ -88,15 +91,14 @@ pub fn run(ctx: &mut Context, key_state: u8) {
                 &mut ctx.keypad,
                 &mut ctx.dma,
                 &mut ctx.mmc,
+                &mut ctx.cycles,
+                &mut ctx.open_bus,
             );
-            cpu::step(&mut ctx.cpu_registers, &mut cpu_bus, &mut ctx.nmi) as u16
+            ctx.cpu.step(&mut ctx.cpu_registers, &mut cpu_bus);
+            cpu_bus.is_frame_ready()
         };
-        ctx.apu.run(cycle);
-        let is_ready = ctx.ppu.run((cycle * 3) as usize, &mut ctx.nmi, &ctx.mmc);
         if is_ready {
-            if ctx.ppu.background.0.len() != 0 {
-                ctx.renderer.render(&ctx.ppu.background.0, &ctx.ppu.sprites);
-            }
+            ctx.renderer.render(&ctx.ppu.frame);
             break;
         }
     }
@@ -110,34 +112,187 @@ impl Context {
     pub fn new(buf: &mut [Data]) -> Self {
         let cassette = parser::parse(buf);
         Context {
-            cpu_registers: cpu_registers::Registers::new(),
+            cpu: Cpu::new(Variant::Ricoh2A03),
+            cpu_registers: Registers::new(),
             program_rom: Rom::new(cassette.program_rom),
             ppu: Ppu::new(
                 cassette.character_ram,
                 PpuConfig {
                     is_horizontal_mirror: cassette.is_horizontal_mirror,
+                    has_sprite_limit: true,
+                    region: cassette.region,
+                    has_oam_decay: false,
                 },
             ),
             work_ram: Ram::new(vec![0; 0x0800]),
             keypad: Keypad::new(),
             dma: Dma::new(),
-            apu: Apu::new(),
-            nmi: false,
+            apu: Apu::new(cassette.region),
             mmc: Mmc::new(cassette.mapper, 0),
             renderer: Renderer::new(),
+            cycles: 0,
+            open_bus: 0,
+        }
+    }
+
+    // Pressing the reset button: RAM is kept and the CPU only loses 3 bytes of stack.
+    pub fn soft_reset(&mut self) {
+        self.ppu.reset();
+        self.apu.reset();
+        self.dma.reset();
+        let mut cpu_bus = cpu_bus::Bus::new(
+            &self.program_rom,
+            &mut self.work_ram,
+            &mut self.ppu,
+            &mut self.apu,
+            &mut self.keypad,
+            &mut self.dma,
+            &mut self.mmc,
+            &mut self.cycles,
+            &mut self.open_bus,
+        );
+        self.cpu.soft_reset(&mut self.cpu_registers, &mut cpu_bus);
+    }
+
+    // Enhancement: lift the 8 sprites per line limit to get rid of flicker.
+    // The sprite overflow flag is not affected.
+    pub fn set_sprite_limit(&mut self, enabled: bool) {
+        self.ppu.config.has_sprite_limit = enabled;
+    }
+
+    // Audio generated since the last call, mono at `get_sample_rate` Hz.
+    // Returns the number of samples written to `buf`.
+    pub fn take_audio_samples(&mut self, buf: &mut [f32]) -> usize {
+        self.apu.take_samples(buf)
+    }
+
+    pub fn get_sample_rate(&self) -> usize {
+        self.apu.get_sample_rate()
+    }
+
+    // Rate of the host audio device, 44100Hz by default.
+    pub fn set_sample_rate(&mut self, sample_rate: usize) {
+        self.apu.set_sample_rate(sample_rate);
+    }
+
+    // Dynamic rate control: the audio and video clocks of the host drift
+    // apart, so a frontend that paces by video nudges the sample rate by
+    // a fraction of a percent to keep its audio queue from running dry or
+    // filling up. Reset by `set_sample_rate`.
+    pub fn set_sample_rate_ratio(&mut self, ratio: f64) {
+        self.apu.set_sample_rate_ratio(ratio);
+    }
+
+    // Volume of a single channel, 1.0 is the console's mix and 0.0 mutes it.
+    // `Channel::Expansion` is the level of cartridge audio.
+    pub fn set_channel_volume(&mut self, channel: Channel, volume: f32) {
+        self.apu.mixer.set_volume(channel, volume);
+    }
+
+    pub fn get_channel_volume(&self, channel: Channel) -> f32 {
+        self.apu.mixer.get_volume(channel)
+    }
+
+    // For debugging music: silence channels, or hear only the soloed ones.
+    pub fn set_channel_muted(&mut self, channel: Channel, muted: bool) {
+        self.apu.mixer.set_muted(channel, muted);
+    }
+
+    pub fn set_channel_solo(&mut self, channel: Channel, solo: bool) {
+        self.apu.mixer.set_solo(channel, solo);
+    }
+
+    // Logs every APU register write from now on, see `finish_vgm_log`.
+    pub fn start_vgm_log(&mut self) {
+        // The DMC plays samples from $C000-$FFFF.
+        let size = self.program_rom.size();
+        let offset = if size <= 0x4000 { 0 } else { 0x4000 };
+        let dmc_memory: Vec<u8> = (0..0x4000)
+            .map(|addr| self.program_rom.read(offset + addr))
+            .collect();
+        let cpu_clock = self.ppu.config.region.cpu_clock();
+        self.apu.vgm = Some(VgmLogger::new(cpu_clock, &dmc_memory));
+    }
+
+    // The VGM file logged since `start_vgm_log`.
+    pub fn finish_vgm_log(&mut self) -> Option<Vec<u8>> {
+        self.apu.vgm.take().map(|vgm| vgm.finish())
+    }
+
+    // Read only access for debuggers and visualizers, see `Apu::channel_state`.
+    pub fn get_apu(&self) -> &Apu {
+        &self.apu
+    }
+
+    // Accuracy option: OAM loses its contents when rendering is off for too long.
+    pub fn set_oam_decay(&mut self, enabled: bool) {
+        self.ppu.config.has_oam_decay = enabled;
+    }
+
+    // Runs the program on another 6502 core, the NES has the 2A03.
+    // Takes effect from the next instruction.
+    pub fn set_cpu_variant(&mut self, variant: Variant) {
+        self.cpu = Cpu::new(variant);
+    }
+
+    pub fn get_cpu_variant(&self) -> Variant {
+        self.cpu.get_variant()
+    }
+
+    // The region comes from the ROM header, NTSC when it does not say.
+    // Changing it swaps the console, so it power cycles.
+    pub fn set_region(&mut self, region: Region, init: RamInit) {
+        self.ppu.config.region = region;
+        self.power_cycle(init);
+        // A VGM file has a single clock, a running log starts over.
+        if self.apu.vgm.is_some() {
+            self.start_vgm_log();
         }
     }
+
+    pub fn get_region(&self) -> Region {
+        self.ppu.config.region
+    }
+
+    // Turning the console off and on again, with work RAM filled by `init`.
+    pub fn power_cycle(&mut self, init: RamInit) {
+        let config = PpuConfig {
+            is_horizontal_mirror: self.ppu.config.is_horizontal_mirror,
+            has_sprite_limit: self.ppu.config.has_sprite_limit,
+            region: self.ppu.config.region,
+            has_oam_decay: self.ppu.config.has_oam_decay,
+        };
+        let character_ram = std::mem::take(&mut self.ppu.ctx.cram.field);
+        self.ppu = Ppu::new(character_ram, config);
+        self.work_ram.fill(init);
+        self.cpu_registers = Registers::new();
+        self.keypad = Keypad::new();
+        self.dma = Dma::new();
+        let apu = std::mem::replace(&mut self.apu, Apu::new(self.ppu.config.region));
+        self.apu.set_sample_rate(apu.get_sample_rate());
+        self.apu.mixer = apu.mixer;
+        self.apu.vgm = apu.vgm;
+        self.mmc = Mmc::new(self.mmc.get_mapper(), 0);
+        self.cycles = 0;
+        self.open_bus = 0;
+        reset(self);
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use std::io::BufRead;
+    use std::sync::OnceLock;
 
     use crate::nes::cpu_registers::CpuRegisters;
 
     use super::*;
 
+    // The frontend normally provides the frame hook.
+    #[no_mangle]
+    pub extern "C" fn canvas_render(_ptr: *const u8, _len: usize) {}
+
     struct NesTestLog {
         // program counter
         pc: u16,
@@ -150,7 +305,7 @@ mod tests {
         regs: String,
         // CPU & PPU clock cycles
         _ppu: String,
-        _cycle: u32,
+        cycle: u64,
     }
 
     impl NesTestLog {
@@ -158,22 +313,299 @@ mod tests {
             let pc = &line[0..4];
             let opbytes = &line[6..14];
             let instruction = &line[15..48];
-            lazy_static! {
-                // static ref RE: Regex = Regex::new(r"^A:(?P<A>[0-9A-F]{2}) X:(?P<X>[0-9A-F]{2}) Y:(?P<Y>[0-9A-F]{2}) P:(?P<P>[0-9A-F]{2}) SP:(?P<SP>[0-9A-F]{2}) PPU:(?P<PPU>[0-9 ]+,[0-9 ]+) CYC:(?P<CYC>[0-9]+)$").unwrap();
-                static ref RE: regex::Regex = regex::Regex::new(r"^(?P<regs>.+) PPU:(?P<PPU>[0-9 ]+,[0-9 ]+) CYC:(?P<CYC>[0-9]+)$").unwrap();
-            };
-            let caps = RE.captures(&line[48..]).unwrap();
+            static RE: OnceLock<regex::Regex> = OnceLock::new();
+            // Regex::new(r"^A:(?P<A>[0-9A-F]{2}) X:(?P<X>[0-9A-F]{2}) Y:(?P<Y>[0-9A-F]{2}) P:(?P<P>[0-9A-F]{2}) SP:(?P<SP>[0-9A-F]{2}) PPU:(?P<PPU>[0-9 ]+,[0-9 ]+) CYC:(?P<CYC>[0-9]+)$").unwrap();
+            let re = RE.get_or_init(|| {
+                regex::Regex::new(r"^(?P<regs>.+) PPU:(?P<PPU>[0-9 ]+,[0-9 ]+) CYC:(?P<CYC>[0-9]+)$").unwrap()
+            });
+            let caps = re.captures(&line[48..]).unwrap();
             Self {
                 pc: u16::from_str_radix(pc, 16).unwrap(),
                 opbytes: opbytes.trim().to_string(),
                 _instruction: instruction.trim().to_string(),
                 regs: caps["regs"].to_string(),
                 _ppu: caps["PPU"].to_string(),
-                _cycle: caps["CYC"].parse().unwrap(),
+                cycle: caps["CYC"].parse().unwrap(),
+            }
+        }
+    }
+
+    fn cycles_of<F: FnOnce(&mut cpu_bus::Bus)>(ctx: &mut Context, f: F) -> u64 {
+        let mut cpu_bus = cpu_bus::Bus::new(
+            &ctx.program_rom,
+            &mut ctx.work_ram,
+            &mut ctx.ppu,
+            &mut ctx.apu,
+            &mut ctx.keypad,
+            &mut ctx.dma,
+            &mut ctx.mmc,
+            &mut ctx.cycles,
+            &mut ctx.open_bus,
+        );
+        let start = cpu_bus.get_cycles();
+        f(&mut cpu_bus);
+        cpu_bus.get_cycles() - start
+    }
+
+    #[test]
+    fn test_oam_dma() {
+        use self::cpu_bus::CpuBus;
+        let mut rom = std::fs::read("roms/nestest.nes").unwrap();
+        let mut ctx = Context::new(&mut rom);
+        for i in 0..0x100 {
+            ctx.work_ram.write(0x0200 + i, i as Data);
+        }
+        // write + 513 or 514 DMA cycles + the read the CPU was halted on
+        for &(start, expected) in &[(0, 515), (1, 516)] {
+            ctx.cycles = start;
+            let cycles = cycles_of(&mut ctx, |bus| {
+                bus.write(0x4014, 0x02);
+                bus.read(0x8000);
+            });
+            assert_eq!(cycles, expected);
+        }
+        for i in 0..0x100 {
+            // The unused attribute bits are dropped.
+            let mask = if i & 0x03 == 0x02 { 0xE3 } else { 0xFF };
+            assert_eq!(ctx.ppu.ctx.sprite_ram.read(i), i as Data & mask);
+        }
+    }
+
+    #[test]
+    fn test_dmc_dma_deletes_controller_bit() {
+        use self::cpu_bus::CpuBus;
+        for &(start, expected) in &[(0, 4), (1, 5)] {
+            let mut rom = std::fs::read("roms/nestest.nes").unwrap();
+            let mut ctx = Context::new(&mut rom);
+            // Only A is pressed.
+            ctx.keypad.update(0x01, 0x00);
+            ctx.cycles = start;
+            let mut data = 0xFF;
+            let cycles = cycles_of(&mut ctx, |bus| {
+                bus.write(0x4016, 0x01);
+                bus.write(0x4016, 0x00);
+                bus.write(0x4013, 0x00);
+                bus.write(0x4015, 0x10);
+                data = bus.read(0x4016);
+            });
+            // The read takes 1 cycle, the sample fetch steals 3 or 4.
+            assert_eq!(cycles - 4, expected);
+            // The halted read clocked the controller, so A was skipped.
+            assert_eq!(data & 0x01, 0x00);
+        }
+    }
+
+    #[test]
+    fn test_open_bus() {
+        use self::cpu_bus::CpuBus;
+        let mut rom = std::fs::read("roms/nestest.nes").unwrap();
+        let mut ctx = Context::new(&mut rom);
+        ctx.keypad.update(0x01, 0x00);
+        cycles_of(&mut ctx, |bus| {
+            // Nothing answers at $5000, the last value on the bus is read back.
+            bus.write(0x0000, 0xA5);
+            assert_eq!(bus.read(0x5000), 0xA5);
+            assert_eq!(bus.read(0x6000), 0xA5);
+            assert_eq!(bus.read(0x4000), 0xA5);
+            // Only the low bits of the controller ports are driven.
+            bus.write(0x4016, 0x41);
+            bus.write(0x4016, 0x40);
+            assert_eq!(bus.read(0x4016), 0x41);
+            assert_eq!(bus.read(0x4017), 0x40);
+            // $4015 bit 5 is not driven, and the read does not reach the bus.
+            bus.write(0x0000, 0xFF);
+            assert_eq!(bus.read(0x4015), 0x20);
+            assert_eq!(bus.read(0x5000), 0xFF);
+        });
+    }
+
+    #[test]
+    fn test_soft_reset() {
+        let mut rom = std::fs::read("roms/nestest.nes").unwrap();
+        let mut ctx = Context::new(&mut rom);
+        reset(&mut ctx);
+        ctx.work_ram.write(0x0010, 0xAB);
+        ctx.cpu_registers.set_A(0x12).set_interrupt(false);
+        ctx.ppu.registers.ppu_ctrl1 = 0x80;
+        ctx.ppu.write(0x0006, 0x23, &ctx.mmc);
+        ctx.ppu.write(0x0006, 0x45, &ctx.mmc);
+        let sp = ctx.cpu_registers.get_SP();
+        ctx.soft_reset();
+        assert_eq!(ctx.work_ram.read(0x0010), 0xAB);
+        assert_eq!(ctx.cpu_registers.get_A(), 0x12);
+        assert_eq!(ctx.cpu_registers.get_SP(), sp.wrapping_sub(3));
+        assert!(ctx.cpu_registers.get_interrupt());
+        assert_eq!(ctx.cpu_registers.get_PC(), 0xC004);
+        assert_eq!(ctx.ppu.registers.ppu_ctrl1, 0);
+        // PPUADDR survives the reset.
+        assert_eq!(ctx.ppu.registers.loopy.get_addr(), 0x2345);
+    }
+
+    #[test]
+    fn test_cpu_variant() {
+        let mut rom = std::fs::read("roms/nestest.nes").unwrap();
+        let mut ctx = Context::new(&mut rom);
+        assert_eq!(ctx.get_cpu_variant(), Variant::Ricoh2A03);
+        // SED, CLC, LDA #$09, ADC #$01
+        let program = [0xF8, 0x18, 0xA9, 0x09, 0x69, 0x01];
+        for &(variant, expected) in &[(Variant::Ricoh2A03, 0x0A), (Variant::Nmos6502, 0x10)] {
+            ctx.set_cpu_variant(variant);
+            for (i, &data) in program.iter().enumerate() {
+                ctx.work_ram.write(0x0300 + i as Addr, data);
+            }
+            reset_with_addr(&mut ctx, 0x0300);
+            let mut cpu_bus = cpu_bus::Bus::new(
+                &ctx.program_rom,
+                &mut ctx.work_ram,
+                &mut ctx.ppu,
+                &mut ctx.apu,
+                &mut ctx.keypad,
+                &mut ctx.dma,
+                &mut ctx.mmc,
+                &mut ctx.cycles,
+                &mut ctx.open_bus,
+            );
+            for _ in 0..4 {
+                ctx.cpu.step(&mut ctx.cpu_registers, &mut cpu_bus);
             }
+            // Only the NMOS 6502 has decimal mode.
+            assert_eq!(ctx.cpu_registers.get_A(), expected);
         }
     }
 
+    #[test]
+    fn test_vgm_log() {
+        let mut rom = std::fs::read("roms/nestest.nes").unwrap();
+        let mut ctx = Context::new(&mut rom);
+        reset(&mut ctx);
+        ctx.start_vgm_log();
+        run(&mut ctx, 0, 0);
+        // The reset button clears $4015.
+        ctx.soft_reset();
+        run(&mut ctx, 0, 0);
+        let vgm = ctx.finish_vgm_log().unwrap();
+        assert!(ctx.finish_vgm_log().is_none());
+        assert_eq!(&vgm[0..4], b"Vgm ");
+        // The DMC memory block mirrors the 16KB PRG ROM at $C000.
+        let block = &vgm[0x100..0x100 + 9 + 0x4000];
+        assert_eq!(&block[0..3], &[0x67, 0x66, 0xC2]);
+        assert_eq!(block[9], ctx.program_rom.read(0));
+        let commands = &vgm[0x100 + 9 + 0x4000..];
+        assert!(commands.windows(3).any(|w| w == [0xB4, 0x15, 0x00]));
+        assert_eq!(commands.last(), Some(&0x66));
+    }
+
+    #[test]
+    fn test_vgm_log_region() {
+        let mut rom = std::fs::read("roms/nestest.nes").unwrap();
+        let mut ctx = Context::new(&mut rom);
+        reset(&mut ctx);
+        ctx.start_vgm_log();
+        run(&mut ctx, 0, 0);
+        ctx.set_region(Region::Pal, RamInit::Zeros);
+        let vgm = ctx.finish_vgm_log().unwrap();
+        let clock = u32::from_le_bytes([vgm[0x84], vgm[0x85], vgm[0x86], vgm[0x87]]);
+        assert_eq!(clock as usize, Region::Pal.cpu_clock());
+        // The NTSC frame is not in the new log.
+        assert_eq!(&vgm[0x18..0x1C], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_power_cycle() {
+        let mut rom = std::fs::read("roms/nestest.nes").unwrap();
+        let mut ctx = Context::new(&mut rom);
+        reset(&mut ctx);
+        ctx.cpu_registers.set_A(0x12);
+        ctx.power_cycle(RamInit::Ones);
+        assert!(ctx.work_ram.field.iter().all(|&v| v == 0xFF));
+        assert_eq!(ctx.cpu_registers.get_A(), 0);
+        assert_eq!(ctx.cpu_registers.get_SP(), 0xFD);
+        assert_eq!(ctx.cpu_registers.get_PC(), 0xC004);
+
+        let mut other = Context::new(&mut rom);
+        ctx.power_cycle(RamInit::Random(7));
+        other.power_cycle(RamInit::Random(7));
+        assert_eq!(ctx.work_ram.field, other.work_ram.field);
+    }
+
+    #[test]
+    fn test_sprite_hit() {
+        let roms = [
+            "01.basics",
+            "02.alignment",
+            "03.corners",
+            "04.flip",
+            "05.left_clip",
+            "06.right_edge",
+            "07.screen_bottom",
+            "08.double_height",
+            "09.timing_basics",
+            "10.timing_order",
+            "11.edge_timing",
+        ];
+        for name in roms.iter() {
+            let path = format!("roms/sprite_hit_tests/{}.nes", name);
+            let mut rom = std::fs::read(path).unwrap();
+            let mut ctx = Context::new(&mut rom);
+            reset(&mut ctx);
+            for _ in 0..90 {
+                run(&mut ctx, 0, 0);
+            }
+            // The test result code, 1 means passed.
+            assert_eq!(ctx.work_ram.read(0xF8), 1, "{}", name);
+        }
+    }
+
+    // Left edge of the line drawn by monochrome mode on line 121.
+    fn nmi_sync_edge(ctx: &Context) -> Option<usize> {
+        (0..256).find(|&x| ctx.ppu.frame[121 * 256 + x] & 0x3F == 0x30)
+    }
+
+    #[test]
+    fn test_nmi_sync() {
+        let mut rom = std::fs::read("roms/nmi_sync/demo_ntsc.nes").unwrap();
+        let mut ctx = Context::new(&mut rom);
+        reset(&mut ctx);
+        // Synchronizing takes a little under 30 frames.
+        for _ in 0..40 {
+            run(&mut ctx, 0, 0);
+        }
+        // Once synchronized the $2001 write lands on the same dot every
+        // other frame, and one dot later in between. As on hardware the edge
+        // is at x = 80 and 81.
+        for i in 0..120 {
+            run(&mut ctx, 0, 0);
+            assert_eq!(nmi_sync_edge(&ctx), Some(80 + i % 2), "frame {}", i);
+        }
+        // The reference lines above and below start at x = 80.
+        assert_eq!(ctx.ppu.frame[119 * 256 + 80] & 0x3F, 0x30);
+        assert_eq!(ctx.ppu.frame[119 * 256 + 79] & 0x3F, 0x3F);
+        assert_eq!(ctx.ppu.frame[123 * 256 + 80] & 0x3F, 0x30);
+    }
+
+    #[test]
+    fn test_nmi_sync_pal() {
+        let mut rom = std::fs::read("roms/nmi_sync/demo_pal.nes").unwrap();
+        let mut ctx = Context::new(&mut rom);
+        // The header does not say PAL.
+        ctx.set_region(Region::Pal, RamInit::Zeros);
+        for _ in 0..40 {
+            run(&mut ctx, 0, 0);
+        }
+        // The upper reference line starts at x = 82, the farthest left the
+        // edge can be, and the lower one at 84, the farthest right. Where it
+        // falls in between depends on how the CPU and PPU clocks line up at
+        // power on, which is fixed here: the edge is at 83 and 82 in turn.
+        for i in 0..120 {
+            run(&mut ctx, 0, 0);
+            assert_eq!(nmi_sync_edge(&ctx), Some(83 - i % 2), "frame {}", i);
+        }
+        assert_eq!(ctx.ppu.frame[119 * 256 + 82] & 0x3F, 0x30);
+        assert_eq!(ctx.ppu.frame[119 * 256 + 81] & 0x3F, 0x3F);
+        assert_eq!(ctx.ppu.frame[123 * 256 + 84] & 0x3F, 0x30);
+        assert_eq!(ctx.ppu.frame[123 * 256 + 83] & 0x3F, 0x3F);
+    }
+
     #[test]
     // wget "http://nickmass.com/images/nestest.nes" -O resources/nestest.nes
     // wget "https://www.qmtpro.com/~nes/misc/nestest.log" -O resources/nestest.log
@@ -203,6 +635,8 @@ mod tests {
             &mut ctx.keypad,
             &mut ctx.dma,
             &mut ctx.mmc,
+            &mut ctx.cycles,
+            &mut ctx.open_bus,
         );
 
         for (lineno, line_) in result_lines {
@@ -232,7 +666,16 @@ mod tests {
                 lineno,
                 line
             );
-            cpu::step(&mut ctx.cpu_registers, &mut cpu_bus, &mut ctx.nmi);
+            // The log starts after the 7 cycles of the reset sequence,
+            // which reset_with_addr skips.
+            assert_eq!(
+                cpu_bus.get_cycles() + 7,
+                expect_res.cycle,
+                "@ lineno={}, line={}",
+                lineno,
+                line
+            );
+            ctx.cpu.step(&mut ctx.cpu_registers, &mut cpu_bus);
         }
     }
 }
@@ -1,5 +1,7 @@
 use std::str;
 
+use super::region::Region;
+
 const NES_HEADER_SIZE: usize = 0x0010;
 const PROGRAM_ROM_SIZE: usize = 0x4000;
 const CHARACTER_ROM_SIZE: usize = 0x2000;
@@ -9,6 +11,7 @@ pub struct Cassette {
     pub character_ram: Vec<u8>,
     pub program_rom: Vec<u8>,
     pub mapper: u8,
+    pub region: Region,
 }
 
 pub fn parse(buf: &mut [u8]) -> Cassette {
@@ -25,12 +28,60 @@ pub fn parse(buf: &mut [u8]) -> Cassette {
     let is_horizontal_mirror = (buf[6] & 0x01) != 0x01;
     let mapper = ((buf[6] & 0xF0) >> 4) | buf[7] & 0xF0;
     println!("mapper type is {}", mapper);
+    let region = parse_region(buf);
     let character_rom_start = NES_HEADER_SIZE + program_rom_pages * PROGRAM_ROM_SIZE;
     let character_rom_end = character_rom_start + character_rom_pages * CHARACTER_ROM_SIZE;
+    // No character rom means the cartridge has 8KB of character ram instead.
+    let character_ram = if character_rom_pages == 0 {
+        vec![0; CHARACTER_ROM_SIZE]
+    } else {
+        buf[character_rom_start..character_rom_end].to_vec()
+    };
     Cassette {
         is_horizontal_mirror,
         program_rom: buf[NES_HEADER_SIZE..character_rom_start].to_vec(),
-        character_ram: buf[character_rom_start..character_rom_end].to_vec(),
+        character_ram,
         mapper,
+        region,
+    }
+}
+
+// NES 2.0 has a timing field in byte 12. iNES 1.0 only has a PAL bit in byte 9,
+// which is only trusted when the unused bytes are clean, as old dumps often
+// have junk like "DiskDude!" there.
+// There is no lookup of the ROM checksum in a game database, so a PAL game
+// whose header does not say so (roms/nmi_sync/demo_pal.nes) boots as NTSC
+// until the frontend sets the region.
+fn parse_region(buf: &[u8]) -> Region {
+    let is_nes2 = buf[7] & 0x0C == 0x08;
+    if is_nes2 {
+        return match buf[12] & 0x03 {
+            1 => Region::Pal,
+            3 => Region::Dendy,
+            // 2 runs on both, NTSC is picked.
+            _ => Region::Ntsc,
+        };
+    }
+    if buf[12..16].iter().all(|&b| b == 0) && buf[9] & 0x01 == 0x01 {
+        Region::Pal
+    } else {
+        Region::Ntsc
     }
 }
+
+#[test]
+fn test_parse_region() {
+    let mut header = [0u8; 16];
+    header[0..4].copy_from_slice(b"NES\x1a");
+    assert_eq!(parse_region(&header), Region::Ntsc);
+    header[9] = 0x01;
+    assert_eq!(parse_region(&header), Region::Pal);
+    header[15] = b'!';
+    assert_eq!(parse_region(&header), Region::Ntsc);
+    // NES 2.0
+    header[7] = 0x08;
+    header[12] = 0x03;
+    assert_eq!(parse_region(&header), Region::Dendy);
+    header[12] = 0x02;
+    assert_eq!(parse_region(&header), Region::Ntsc);
+}
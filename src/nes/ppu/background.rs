@@ -1,65 +1,78 @@
-// use std::cell::Cell;
-
-use super::super::mmc::*;
-use super::super::ram::Ram;
-use super::super::types::{Addr, Data};
-use super::palette::*;
-use super::sprite_utils::*;
-use super::tile::Tile;
+use super::super::types::Data;
 
+// Background fetch pipeline.
+// Every 8 dots the PPU fetches a nametable byte, an attribute byte and the two
+// pattern bytes of the next tile into latches, and the latches are loaded into
+// the low half of 16 bit shift registers. The pixel is picked from the high half
+// by fine X, so the registers always hold the current and the next tile.
 #[derive(Debug)]
-pub struct BackgroundCtx {
-    pub tile: Tile,
-    pub scroll_x: Data,
-    pub scroll_y: Data,
-    pub is_enabled: bool,
+pub struct Background {
+    pub name_table: Data,
+    pub attribute: Data,
+    pub pattern_low: Data,
+    pub pattern_high: Data,
+    pattern_low_shift: u16,
+    pattern_high_shift: u16,
+    attribute_low_shift: u16,
+    attribute_high_shift: u16,
 }
 
-pub type BackgroundField = Vec<BackgroundCtx>;
-
-#[derive(Debug)]
-pub struct Background(pub BackgroundField);
-
-const TILE_PER_LINE: u8 = 32;
-
 impl Background {
     pub fn new() -> Self {
-        Background(Vec::new())
+        Background {
+            name_table: 0,
+            attribute: 0,
+            pattern_low: 0,
+            pattern_high: 0,
+            pattern_low_shift: 0,
+            pattern_high_shift: 0,
+            attribute_low_shift: 0,
+            attribute_high_shift: 0,
+        }
     }
 
-    pub fn clear(&mut self) {
-        self.0 = Vec::new();
+    pub fn shift(&mut self) {
+        self.pattern_low_shift <<= 1;
+        self.pattern_high_shift <<= 1;
+        self.attribute_low_shift <<= 1;
+        self.attribute_high_shift <<= 1;
     }
 
-    pub fn build_line<P: PaletteRam>(
-        &mut self,
-        vram: &Ram,
-        cram: &Ram,
-        palette: &P,
-        tile: (u8, u8),
-        scroll: (u8, u8),
-        config: &mut SpriteConfig,
-        mmc: &Mmc,
-    ) {
-        // INFO: Horizontal offsets range from 0 to 255. "Normal" vertical offsets range from 0 to 239,
-        // while values of 240 to 255 are treated as -16 through -1 in a way, but tile data is incorrectly
-        // fetched from the attribute table.
-        let clamped_tile_y = tile.1 % 30;
-        let table_id_offset = if (tile.1 / 30) % 2 == 0 { 0 } else { 2 };
-        // background of a line.
-        // Build viewport + 1 tile for background scroll.
-        for x in 0..(TILE_PER_LINE + 1) {
-            let tile_x = x + tile.0;
-            let clamped_tile_x = tile_x % TILE_PER_LINE;
-            let name_table_id = ((tile_x / TILE_PER_LINE) % 2) + table_id_offset;
-            config.offset_addr_by_name_table = Some((name_table_id as Addr) * 0x400);
-            let position: SpritePosition = (clamped_tile_x as u8, clamped_tile_y as u8);
-            self.0.push(BackgroundCtx {
-                tile: Tile::new(vram, cram, palette, &position, &config, &mmc),
-                scroll_x: scroll.0,
-                scroll_y: scroll.1,
-                is_enabled: config.is_background_enable,
-            });
+    pub fn reload(&mut self) {
+        self.pattern_low_shift = (self.pattern_low_shift & 0xFF00) | self.pattern_low as u16;
+        self.pattern_high_shift = (self.pattern_high_shift & 0xFF00) | self.pattern_high as u16;
+        // The attribute bits are the same for the whole tile.
+        let attribute = self.attribute;
+        let fill = |bit: Data| if attribute & bit != 0 { 0xFF } else { 0x00 };
+        self.attribute_low_shift = (self.attribute_low_shift & 0xFF00) | fill(0x01);
+        self.attribute_high_shift = (self.attribute_high_shift & 0xFF00) | fill(0x02);
+    }
+
+    // Palette address of the current pixel, 0 when transparent.
+    pub fn pixel(&self, fine_x: Data) -> Data {
+        let bit = 0x8000 >> fine_x;
+        let color = ((self.pattern_low_shift & bit != 0) as Data)
+            | (((self.pattern_high_shift & bit != 0) as Data) << 1);
+        if color == 0 {
+            return 0;
         }
+        let palette_id = ((self.attribute_low_shift & bit != 0) as Data)
+            | (((self.attribute_high_shift & bit != 0) as Data) << 1);
+        palette_id << 2 | color
+    }
+}
+
+#[test]
+fn pixel_is_picked_by_fine_x() {
+    let mut bg = Background::new();
+    bg.pattern_low = 0x80;
+    bg.pattern_high = 0x40;
+    bg.attribute = 0x02;
+    bg.reload();
+    for _ in 0..8 {
+        bg.shift();
     }
+    assert_eq!(bg.pixel(0), 0x09);
+    assert_eq!(bg.pixel(1), 0x0A);
+    assert_eq!(bg.pixel(2), 0x00);
 }
@@ -1,23 +1,28 @@
-pub mod background;
+mod background;
 mod palette;
 mod registers;
 mod sprite;
 mod sprite_utils;
-pub mod tile;
 
 use self::super::mmc::Mmc;
 use self::super::ram::Ram;
+use self::super::region::Region;
 pub use self::background::*;
 pub use self::palette::*;
 use self::registers::*;
 pub use self::sprite::*;
 pub use self::sprite_utils::*;
-pub use self::tile::*;
 use super::types::{Addr, Data};
 
 #[derive(Debug)]
 pub struct PpuConfig {
     pub is_horizontal_mirror: bool,
+    // Real hardware draws at most 8 sprites per line, which makes them
+    // flicker in busy scenes. Turning this off draws all of them.
+    pub has_sprite_limit: bool,
+    pub region: Region,
+    // Sprites decay when rendering is off for too long, which some test ROMs check.
+    pub has_oam_decay: bool,
 }
 
 #[derive(Debug)]
@@ -28,7 +33,32 @@ pub struct PpuCtx<P: PaletteRam> {
     pub sprite_ram: Box<Ram>,
 }
 
+// PPU memory map, shared by rendering and $2007.
+impl<P: PaletteRam> PpuCtx<P> {
+    pub fn read(&self, addr: Addr, config: &PpuConfig, mmc: &Mmc) -> Data {
+        match addr & 0x3FFF {
+            addr @ 0x0000..=0x1FFF => self.cram.read(mmc.create_chram_addr(addr)),
+            addr @ 0x2000..=0x3EFF => self
+                .vram
+                .read(mirror_down_name_table_addr(addr, config.is_horizontal_mirror)),
+            addr => self.palette.read(addr & 0x1F),
+        }
+    }
+
+    pub fn write(&mut self, addr: Addr, data: Data, config: &PpuConfig, mmc: &Mmc) {
+        match addr & 0x3FFF {
+            addr @ 0x0000..=0x1FFF => self.cram.write(mmc.create_chram_addr(addr), data),
+            addr @ 0x2000..=0x3EFF => self.vram.write(
+                mirror_down_name_table_addr(addr, config.is_horizontal_mirror),
+                data,
+            ),
+            addr => self.palette.write(addr & 0x1F, data),
+        }
+    }
+}
+
 const CYCLES_PER_LINE: usize = 341;
+const VISIBLE_LINES: usize = 240;
 
 #[derive(Debug)]
 pub struct Ppu {
@@ -36,9 +66,24 @@ pub struct Ppu {
     pub line: usize,
     pub registers: Registers,
     pub ctx: PpuCtx<Palette>,
-    pub sprites: SpritesWithCtx,
+    pub sprites: SpriteLine,
     pub background: Background,
     pub config: PpuConfig,
+    // Colour index of every pixel with the emphasis bits on top (0-511), 256x240.
+    pub frame: Vec<u16>,
+    // Master clock ticks not yet turned into dots, PAL runs 3.2 dots per CPU cycle.
+    master_clock: usize,
+    // Dots since power on, the clock of OAM decay.
+    dots: u64,
+    is_odd_frame: bool,
+    // Reading $2002 just before vblank starts keeps the flag from being set.
+    is_vblank_suppressed: bool,
+    // Level of the NMI output the last time the CPU sampled it.
+    nmi_line: bool,
+    // Rising edge seen by the CPU.
+    nmi: bool,
+    // Edge seen before the last cycle, the CPU acts on this one.
+    is_nmi_ready: bool,
 }
 
 impl Ppu {
@@ -50,113 +95,557 @@ impl Ppu {
             registers: Registers::new(),
             ctx: PpuCtx {
                 palette: Palette::new(),
-                vram: Box::new(Ram::new(vec![0; 0x2000])),
+                vram: Box::new(Ram::new(vec![0; 0x0800])),
                 cram: Box::new(Ram::new(character_ram)),
                 sprite_ram: Box::new(Ram::new(vec![0; 0x0100])),
             },
-            sprites: Vec::new(),
+            sprites: SpriteLine::new(),
             background: Background::new(),
             config,
+            frame: vec![0; 256 * VISIBLE_LINES],
+            master_clock: 0,
+            dots: 0,
+            is_odd_frame: false,
+            is_vblank_suppressed: false,
+            nmi_line: false,
+            nmi: false,
+            is_nmi_ready: false,
+        }
+    }
+
+    // The reset button clears some registers, memory is left untouched.
+    pub fn reset(&mut self) {
+        self.registers.reset();
+        self.nmi_line = false;
+        self.nmi = false;
+        self.is_nmi_ready = false;
+    }
+
+    pub fn read(&mut self, addr: Addr, mmc: &Mmc) -> Data {
+        // One dot before vblank starts, the read sees the flag clear and the
+        // flag is not set for this frame. A read on the dot it is set or the
+        // one after clears it before the CPU sees the NMI.
+        if addr == 0x0002 && self.line == self.config.region.vblank_line() && self.cycle == 1 {
+            self.is_vblank_suppressed = true;
+        }
+        match addr {
+            0x0004 => self.read_oam_data(),
+            0x0007 => self.read_ppu_data(mmc),
+            _ => self.registers.read(addr),
         }
     }
 
-    pub fn read(&mut self, addr: Addr) -> Data {
-        self.registers.read(addr, &mut self.ctx)
+    pub fn write(&mut self, addr: Addr, data: Data, mmc: &Mmc) {
+        self.registers.latch.write(data);
+        match addr {
+            0x0004 => self.write_oam_data(data),
+            0x0007 => self.write_ppu_data(data, mmc),
+            _ => self.registers.write(addr, data),
+        }
+    }
+
+    // While rendering, $2004 reads what sprite evaluation and the sprite fetch
+    // are looking at. Evaluation runs all at once at dot 257 here, so during
+    // 65-256 primary OAM at OAMADDR is returned.
+    fn read_oam_data(&mut self) -> Data {
+        let data = if self.is_rendering() {
+            match self.cycle {
+                // Secondary OAM is being cleared.
+                1..=64 => 0xFF,
+                65..=256 => self.registers.oam.read_data(&self.ctx.sprite_ram),
+                257..=320 => {
+                    let dot = self.cycle - 257;
+                    self.sprites
+                        .read_secondary_oam(dot / 8 * 4 + std::cmp::min(dot % 8, 3))
+                }
+                _ => self.sprites.read_secondary_oam(0),
+            }
+        } else {
+            if self.config.has_oam_decay {
+                self.registers
+                    .oam
+                    .refresh_row(&mut self.ctx.sprite_ram, self.dots);
+            }
+            self.registers.oam.read_data(&self.ctx.sprite_ram)
+        };
+        self.registers.latch.write(data);
+        data
+    }
+
+    fn write_oam_data(&mut self, data: Data) {
+        if self.is_rendering() {
+            self.registers.oam.write_data_while_rendering();
+            return;
+        }
+        if self.config.has_oam_decay {
+            self.registers
+                .oam
+                .refresh_row(&mut self.ctx.sprite_ram, self.dots);
+        }
+        self.registers
+            .oam
+            .write_data(&mut self.ctx.sprite_ram, data);
+    }
+
+    fn read_ppu_data(&mut self, mmc: &Mmc) -> Data {
+        let addr = self.registers.loopy.get_addr();
+        let data = self
+            .registers
+            .ppu_data
+            .read(addr, &self.ctx, &self.config, mmc);
+        self.increment_ppu_addr();
+        // Palette entries are 6 bits wide, the top 2 bits come from the latch.
+        let mask = if addr >= 0x3F00 { 0x3F } else { 0xFF };
+        self.registers.latch.write_bits(data, mask);
+        self.registers.latch.read()
+    }
+
+    fn write_ppu_data(&mut self, data: Data, mmc: &Mmc) {
+        let addr = self.registers.loopy.get_addr();
+        self.ctx.write(addr, data, &self.config, mmc);
+        self.increment_ppu_addr();
+    }
+
+    // While rendering, v is busy fetching tiles and a $2007 access
+    // increments coarse X and Y at the same time instead of adding 1 or 32.
+    fn increment_ppu_addr(&mut self) {
+        if self.is_rendering() {
+            self.registers.loopy.increment_x();
+            self.registers.loopy.increment_y();
+        } else {
+            let v = self.registers.get_ppu_addr_increment_value() as Addr;
+            self.registers.loopy.increment(v);
+        }
     }
 
-    pub fn write(&mut self, addr: Addr, data: Data) {
-        self.registers.write(addr, data, &mut self.ctx);
+    fn is_rendering(&self) -> bool {
+        self.registers.is_rendering_enabled()
+            && (self.line < VISIBLE_LINES || self.line == self.config.region.pre_render_line())
     }
 
     // The PPU draws one line at 341 clocks and prepares for the next line.
     // While drawing the BG and sprite at the first 256 clocks,
     // it searches for sprites to be drawn on the next scan line.
     // Get the pattern of the sprite searched with the remaining clock.
-    pub fn run(&mut self, cycle: usize, nmi: &mut bool, mmc: &Mmc) -> bool {
-        let cycle = self.cycle + cycle;
-        if cycle < CYCLES_PER_LINE {
-            self.cycle = cycle;
-            return false;
+    pub fn run(&mut self, cycle: usize, mmc: &Mmc) -> bool {
+        let mut is_ready = false;
+        for _ in 0..cycle {
+            if self.step(mmc) {
+                is_ready = true;
+            }
         }
+        is_ready
+    }
 
-        if self.line == 0 {
-            self.background.clear();
-        }
-
-        if self.has_sprite_hit(cycle) {
-            self.registers.set_sprite_hit();
-        }
-
-        self.cycle = cycle - CYCLES_PER_LINE;
-        self.line = self.line + 1;
-
-        let scroll_x = self.registers.get_scroll_x();
-        let scroll_y = self.registers.get_scroll_y();
-        if self.line <= 240 && self.line % 8 == 0 && scroll_y <= 240 {
-            let mut config = SpriteConfig {
-                offset_addr_by_name_table: None,
-                offset_addr_by_background_table: self.registers.get_background_table_offset(),
-                offset_addr_by_sprite_table: self.registers.get_sprite_table_offset(),
-                is_horizontal_mirror: self.config.is_horizontal_mirror,
-                is_background_enable: self.registers.is_background_enable(),
-            };
-            let tile_x = ((scroll_x as usize
-                + (self.registers.get_name_table_id() % 2) as usize * 256)
-                / 8) as u8;
-            let tile_y = self.get_scroll_tile_y();
-            self.background.build_line(
-                &self.ctx.vram,
-                &self.ctx.cram,
-                &self.ctx.palette,
-                (tile_x, tile_y),
-                (scroll_x, scroll_y),
-                &mut config,
-                &mmc,
-            );
-        }
-
-        if self.line == 241 {
-            self.registers.set_vblank();
-            self.registers.clear_sprite_hit();
-            if self.registers.is_irq_enable() {
-                *nmi = true;
+    // Advances the dots of one CPU cycle.
+    pub fn run_cpu_cycle(&mut self, mmc: &Mmc) -> bool {
+        let (_, dots) = self.start_cpu_cycle();
+        self.run(dots, mmc)
+    }
+
+    // Dots of the next CPU cycle, and how many of them come before its
+    // middle, where a register write lands. NTSC has 1 of 3 before the
+    // write, PAL 1 or 2 of 3 or 4.
+    pub fn start_cpu_cycle(&mut self) -> (usize, usize) {
+        let region = self.config.region;
+        let divider = region.ppu_divider();
+        let before = (self.master_clock + region.cpu_divider() / 2) / divider;
+        self.master_clock += region.cpu_divider();
+        let dots = self.master_clock / divider;
+        self.master_clock %= divider;
+        (before, dots)
+    }
+
+    // Runs a single dot, returns true when the frame is done.
+    fn step(&mut self, mmc: &Mmc) -> bool {
+        self.dots += 1;
+        // Rendering keeps OAM refreshed, checked once a line.
+        if self.config.has_oam_decay && self.cycle == 0 && self.registers.is_rendering_enabled() {
+            self.registers
+                .oam
+                .refresh_all(&mut self.ctx.sprite_ram, self.dots);
+        }
+        let pre_render_line = self.config.region.pre_render_line();
+        if self.line < VISIBLE_LINES || self.line == pre_render_line {
+            self.run_rendering(mmc);
+        }
+
+        if self.line == self.config.region.vblank_line() && self.cycle == 1 {
+            if !self.is_vblank_suppressed {
+                self.registers.set_vblank();
             }
+            self.is_vblank_suppressed = false;
         }
 
-        if self.line >= 262 {
+        if self.line == pre_render_line && self.cycle == 1 {
             self.registers.clear_vblank();
             self.registers.clear_sprite_hit();
-            *nmi = false;
-            self.line = 0;
-            self.sprites = build_sprites(
-                &self.ctx.cram,
-                &self.ctx.sprite_ram,
-                &self.ctx.palette,
-                self.registers.get_sprite_table_offset(),
-                self.registers.is_sprite_8x8(),
-                &mmc,
-            );
-            return true;
+            self.registers.clear_sprite_overflow();
+        }
+
+        self.cycle += 1;
+        // The last dot of the pre-render line is skipped on odd frames
+        // while rendering, which keeps the picture from crawling.
+        if self.line == pre_render_line
+            && self.cycle == CYCLES_PER_LINE - 1
+            && self.is_odd_frame
+            && self.config.region.has_odd_frame_skip()
+            && self.registers.is_rendering_enabled()
+        {
+            self.cycle = CYCLES_PER_LINE;
+        }
+        if self.cycle < CYCLES_PER_LINE {
+            return false;
+        }
+        self.cycle = 0;
+        self.line += 1;
+        if self.line < self.config.region.lines_per_frame() {
+            return false;
+        }
+        self.line = 0;
+        self.is_odd_frame = !self.is_odd_frame;
+        self.registers.latch.set_dot(self.dots);
+        true
+    }
+
+    fn run_rendering(&mut self, mmc: &Mmc) {
+        let dot = self.cycle;
+        if self.line < VISIBLE_LINES && (1..=256).contains(&dot) {
+            self.render_pixel(dot - 1);
+        }
+        if !self.registers.is_rendering_enabled() {
+            return;
         }
-        false
+        if (1..=256).contains(&dot) || (321..=336).contains(&dot) {
+            self.background.shift();
+            self.fetch_background(dot, mmc);
+        }
+        // OAMADDR is cleared during the sprite tile loading interval.
+        if (257..=320).contains(&dot) {
+            self.registers.oam.reset_addr();
+        }
+        match dot {
+            256 => self.registers.loopy.increment_y(),
+            257 => {
+                self.registers.loopy.copy_x();
+                if self.line < VISIBLE_LINES {
+                    let is_overflow = self.sprites.evaluate(
+                        self.line,
+                        &self.ctx.sprite_ram,
+                        self.registers.is_sprite_8x8(),
+                        self.config.has_sprite_limit,
+                    );
+                    if is_overflow {
+                        self.registers.set_sprite_overflow();
+                    }
+                    self.sprites.build(
+                        self.line,
+                        &self.ctx.cram,
+                        self.registers.get_sprite_table_offset(),
+                        self.registers.is_sprite_8x8(),
+                        mmc,
+                    );
+                } else {
+                    // No sprites are evaluated for the first line.
+                    self.sprites.clear();
+                }
+            }
+            280..=304 if self.line == self.config.region.pre_render_line() => {
+                self.registers.loopy.copy_y()
+            }
+            _ => (),
+        }
+    }
+
+    // Each fetch takes 2 dots, the next tile is loaded into the shifters
+    // and coarse X is incremented on the 8th dot.
+    fn fetch_background(&mut self, dot: usize, mmc: &Mmc) {
+        let loopy = &self.registers.loopy;
+        match dot % 8 {
+            1 => self.background.name_table = self.read_name_table(loopy.get_tile_addr()),
+            3 => {
+                let attribute = self.read_name_table(loopy.get_attribute_addr());
+                self.background.attribute = (attribute >> loopy.get_attribute_shift()) & 0x03;
+            }
+            5 => {
+                let addr = self.get_background_pattern_addr();
+                self.background.pattern_low = self.read_pattern(addr, mmc);
+            }
+            7 => {
+                let addr = self.get_background_pattern_addr() + 8;
+                self.background.pattern_high = self.read_pattern(addr, mmc);
+            }
+            0 => {
+                self.background.reload();
+                self.registers.loopy.increment_x();
+            }
+            _ => (),
+        }
+    }
+
+    fn get_background_pattern_addr(&self) -> Addr {
+        self.registers.get_background_table_offset()
+            + self.background.name_table as Addr * 16
+            + self.registers.loopy.get_fine_y()
+    }
+
+    fn read_name_table(&self, addr: Addr) -> Data {
+        let addr = mirror_down_name_table_addr(addr, self.config.is_horizontal_mirror);
+        self.ctx.vram.read(addr)
+    }
+
+    fn read_pattern(&self, addr: Addr, mmc: &Mmc) -> Data {
+        self.ctx.read(addr, &self.config, mmc)
+    }
+
+    fn render_pixel(&mut self, x: usize) {
+        // Bits 1 and 2 of $2001 show the background and sprites in the leftmost 8 pixels.
+        let is_left = x < 8;
+        let background = if self.registers.is_background_enable()
+            && (!is_left || self.registers.is_background_masked())
+        {
+            self.background.pixel(self.registers.loopy.get_fine_x())
+        } else {
+            0
+        };
+        let sprite_pixel = self.sprites.get(x);
+        let sprite = if self.registers.is_sprite_enable()
+            && (!is_left || self.registers.is_sprite_masked())
+        {
+            sprite_pixel.color
+        } else {
+            0
+        };
+        let addr = match (background & 0x03, sprite) {
+            (0, 0) => 0,
+            (0, _) => sprite,
+            (_, 0) => background,
+            _ => {
+                // Sprite 0 hit never happens at x = 255.
+                if sprite_pixel.is_sprite_zero && x != 255 {
+                    self.registers.set_sprite_hit();
+                }
+                if sprite_pixel.is_behind_background {
+                    background
+                } else {
+                    sprite
+                }
+            }
+        };
+        let mut color_id = self.ctx.palette.read(addr as Addr) & 0x3F;
+        if self.registers.is_grayscale() {
+            color_id &= 0x30;
+        }
+        // The emphasis bits pick one of the 8 variants of the 64 colours.
+        // PAL and Dendy swap the red and green bits.
+        let mut emphasis = self.registers.get_emphasis();
+        if self.config.region != Region::Ntsc {
+            emphasis = emphasis & 0x04 | (emphasis & 0x01) << 1 | (emphasis & 0x02) >> 1;
+        }
+        self.frame[self.line * 256 + x] = (emphasis as u16) << 6 | color_id as u16;
+    }
+
+    // NMI is output while the vblank flag and $2000 bit 7 are both set.
+    // The CPU samples it at the end of every cycle and looks for a rising
+    // edge, so enabling NMI during vblank triggers another one and clearing
+    // the flag or disabling NMI before the sample cancels it.
+    pub fn poll_nmi(&mut self) {
+        self.is_nmi_ready = self.nmi;
+        let nmi_line = self.registers.is_vblank() && self.registers.is_irq_enable();
+        if nmi_line && !self.nmi_line {
+            self.nmi = true;
+        }
+        self.nmi_line = nmi_line;
     }
 
-    pub fn transfer_sprite(&mut self, addr: Addr, data: Data) {
-        let addr = addr + self.registers.oam.get_addr();
-        self.ctx.sprite_ram.write(addr % 0x100, data);
+    // The CPU polls for NMI before each instruction. An edge seen in the
+    // last cycle of the previous instruction waits for one more instruction.
+    pub fn take_nmi(&mut self) -> bool {
+        if !self.is_nmi_ready {
+            return false;
+        }
+        self.nmi = false;
+        self.is_nmi_ready = false;
+        true
     }
+}
 
-    fn get_scroll_tile_y(&self) -> Data {
-        ((self.registers.get_scroll_y() as usize
-            + self.line
-            + ((self.registers.get_name_table_id() / 2) as usize * 240))
-            / 8) as Data
+#[cfg(test)]
+fn write_ppu_addr(ppu: &mut Ppu, addr: Addr, mmc: &Mmc) {
+    ppu.write(0x0006, (addr >> 8) as Data, mmc);
+    ppu.write(0x0006, addr as Data, mmc);
+}
+
+// Vertical mirroring, 8KB of character RAM.
+#[cfg(test)]
+fn test_ppu(region: Region) -> Ppu {
+    let config = PpuConfig {
+        is_horizontal_mirror: false,
+        has_sprite_limit: true,
+        region,
+        has_oam_decay: false,
+    };
+    Ppu::new(vec![0; 0x2000], config)
+}
+
+#[test]
+fn test_ppu_data_memory_map() {
+    let mmc = Mmc::new(0, 0);
+    let mut ppu = test_ppu(Region::Ntsc);
+    // Vertical mirroring: $2800 is $2000.
+    write_ppu_addr(&mut ppu, 0x2005, &mmc);
+    ppu.write(0x0007, 0xAB, &mmc);
+    write_ppu_addr(&mut ppu, 0x2805, &mmc);
+    ppu.read(0x0007, &mmc);
+    assert_eq!(ppu.read(0x0007, &mmc), 0xAB);
+    // $3F10 is $3F00, and palette reads skip the buffer.
+    write_ppu_addr(&mut ppu, 0x3F10, &mmc);
+    ppu.write(0x0007, 0x21, &mmc);
+    write_ppu_addr(&mut ppu, 0x3F00, &mmc);
+    assert_eq!(ppu.read(0x0007, &mmc), 0x21);
+    // The buffer got the nametable byte under the palette, $2F05 is $2705.
+    write_ppu_addr(&mut ppu, 0x2705, &mmc);
+    ppu.write(0x0007, 0xCD, &mmc);
+    write_ppu_addr(&mut ppu, 0x3F05, &mmc);
+    ppu.read(0x0007, &mmc);
+    write_ppu_addr(&mut ppu, 0x0000, &mmc);
+    assert_eq!(ppu.read(0x0007, &mmc), 0xCD);
+}
+
+#[test]
+fn test_ppu_data_increment_while_rendering() {
+    let mmc = Mmc::new(0, 0);
+    let mut ppu = test_ppu(Region::Ntsc);
+    ppu.write(0x0001, 0x08, &mmc);
+    ppu.line = ppu.config.region.vblank_line();
+    write_ppu_addr(&mut ppu, 0x2000, &mmc);
+    ppu.read(0x0007, &mmc);
+    assert_eq!(ppu.registers.loopy.get_addr(), 0x2001);
+    // Coarse X and fine Y are both incremented.
+    ppu.line = 10;
+    ppu.read(0x0007, &mmc);
+    assert_eq!(ppu.registers.loopy.get_addr(), 0x3002);
+}
+
+#[test]
+fn test_open_bus() {
+    let mmc = Mmc::new(0, 0);
+    let mut ppu = test_ppu(Region::Ntsc);
+    ppu.write(0x0003, 0xA5, &mmc);
+    assert_eq!(ppu.read(0x0000, &mmc), 0xA5);
+    assert_eq!(ppu.read(0x0005, &mmc), 0xA5);
+    // Only the top 3 bits of $2002 are driven.
+    ppu.registers.set_vblank();
+    assert_eq!(ppu.read(0x0002, &mmc), 0x85);
+    // Palette reads drive the low 6 bits.
+    write_ppu_addr(&mut ppu, 0x3F01, &mmc);
+    ppu.write(0x0007, 0x2A, &mmc);
+    write_ppu_addr(&mut ppu, 0x3F01, &mmc);
+    ppu.write(0x0000, 0xC0, &mmc);
+    assert_eq!(ppu.read(0x0007, &mmc), 0xEA);
+}
+
+#[test]
+fn test_vblank_suppression() {
+    let mmc = Mmc::new(0, 0);
+    let mut ppu = test_ppu(Region::Ntsc);
+    ppu.write(0x0000, 0x80, &mmc);
+    // Read one dot before the flag is set.
+    ppu.line = ppu.config.region.vblank_line();
+    ppu.cycle = 1;
+    assert_eq!(ppu.read(0x0002, &mmc) & 0x80, 0x00);
+    ppu.run(3, &mmc);
+    ppu.poll_nmi();
+    ppu.poll_nmi();
+    assert!(!ppu.registers.is_vblank());
+    assert!(!ppu.take_nmi());
+}
+
+#[test]
+fn test_nmi_retrigger() {
+    let mmc = Mmc::new(0, 0);
+    let mut ppu = test_ppu(Region::Ntsc);
+    ppu.write(0x0000, 0x80, &mmc);
+    ppu.registers.set_vblank();
+    ppu.poll_nmi();
+    ppu.poll_nmi();
+    assert!(ppu.take_nmi());
+    // Toggling $2000 bit 7 during vblank gives another edge.
+    ppu.write(0x0000, 0x00, &mmc);
+    ppu.poll_nmi();
+    ppu.write(0x0000, 0x80, &mmc);
+    ppu.poll_nmi();
+    // Not taken until one more cycle has passed.
+    assert!(!ppu.take_nmi());
+    ppu.poll_nmi();
+    assert!(ppu.take_nmi());
+}
+
+#[test]
+fn test_odd_frame_skip() {
+    let mmc = Mmc::new(0, 0);
+    let mut ppu = test_ppu(Region::Ntsc);
+    ppu.write(0x0001, 0x08, &mmc);
+    let frame = CYCLES_PER_LINE * ppu.config.region.lines_per_frame();
+    // Even frame is full length.
+    assert!(!ppu.run(frame - 1, &mmc));
+    assert!(ppu.run(1, &mmc));
+    // Odd frame is one dot short.
+    assert!(!ppu.run(frame - 2, &mmc));
+    assert!(ppu.run(1, &mmc));
+}
+
+#[test]
+fn test_pal_timing() {
+    let mmc = Mmc::new(0, 0);
+    let mut ppu = test_ppu(Region::Pal);
+    ppu.write(0x0001, 0x08, &mmc);
+    // 16 dots every 5 CPU cycles.
+    for _ in 0..5 {
+        ppu.run_cpu_cycle(&mmc);
+    }
+    assert_eq!(ppu.cycle, 16);
+    // 312 lines and no odd frame skip.
+    let frame = CYCLES_PER_LINE * 312;
+    for _ in 0..2 {
+        assert!(!ppu.run(frame - 17, &mmc));
+        assert!(ppu.run(1, &mmc));
+        ppu.run(16, &mmc);
+    }
+}
+
+#[test]
+fn test_pal_emphasis() {
+    let mmc = Mmc::new(0, 0);
+    for &(region, emphasis) in &[(Region::Ntsc, 0x01), (Region::Pal, 0x02)] {
+        let mut ppu = test_ppu(region);
+        // Bit 5 emphasizes red on NTSC and green on PAL.
+        ppu.write(0x0001, 0x20, &mmc);
+        ppu.run(10, &mmc);
+        assert_eq!(ppu.frame[0] >> 6, emphasis);
     }
+}
 
-    fn has_sprite_hit(&self, cycle: usize) -> bool {
-        let y = self.ctx.sprite_ram.read(0) as usize;
-        let x = self.ctx.sprite_ram.read(3) as usize;
-        // (y == self.line) && self.registers.is_sprite_enable()
-        (y == self.line) && x <= cycle && self.registers.is_sprite_enable()
+#[test]
+fn test_oam_data_while_rendering() {
+    let mmc = Mmc::new(0, 0);
+    let mut ppu = test_ppu(Region::Ntsc);
+    for i in 0..0x100 {
+        ppu.ctx.sprite_ram.write(i, 0x20);
     }
+    ppu.ctx.sprite_ram.write(0x00, 0x10);
+    ppu.ctx.sprite_ram.write(0x01, 0x42);
+    ppu.write(0x0001, 0x18, &mmc);
+    ppu.write(0x0003, 0x08, &mmc);
+    ppu.line = 0x12;
+    ppu.cycle = 10;
+    assert_eq!(ppu.read(0x0004, &mmc), 0xFF);
+    // Writes only bump the sprite number.
+    ppu.write(0x0004, 0x55, &mmc);
+    assert_eq!(ppu.registers.oam.get_addr(), 0x0C);
+    assert_eq!(ppu.ctx.sprite_ram.read(0x08), 0x20);
+    // The tile byte of sprite 0 while it is fetched, OAMADDR is cleared.
+    ppu.run(257 - 10 + 1, &mmc);
+    assert_eq!(ppu.read(0x0004, &mmc), 0x42);
+    assert_eq!(ppu.registers.oam.get_addr(), 0x00);
 }
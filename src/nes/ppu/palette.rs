@@ -28,10 +28,6 @@ impl Palette {
         (addr == 0x10) || (addr == 0x14) || (addr == 0x18) || (addr == 0x1c)
     }
 
-    fn is_background_mirror(&self, addr: Addr) -> bool {
-        (addr == 0x04) || (addr == 0x08) || (addr == 0x0c)
-    }
-
     fn get_palette_addr(&self, addr: Addr) -> Addr {
         let mirror_downed = (addr & 0xFF) % 0x20;
         //NOTE: 0x3f10, 0x3f14, 0x3f18, 0x3f1c is mirror of 0x3f00, 0x3f04, 0x3f08, 0x3f0c
@@ -58,9 +54,6 @@ impl PaletteRam for Palette {
         if self.is_sprite_mirror(addr) {
             return self.0[(addr - 0x10) as usize];
         }
-        if self.is_background_mirror(addr) {
-            return self.0[0x00];
-        }
         self.0[addr as usize]
     }
 
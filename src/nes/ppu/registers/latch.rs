@@ -0,0 +1,67 @@
+use super::super::super::types::Data;
+
+// About 600ms, the PPU runs close to 5.3 million dots per second in every
+// region.
+const DECAY_DOTS: u64 = 3_200_000;
+
+// The PPU I/O latch ("PPU open bus").
+// The data lines between the CPU and the PPU hold the last value transferred,
+// so reading a write only register returns it. Each bit fades to 0 on its own
+// when it has not been driven for a while.
+#[derive(Debug)]
+pub struct Latch {
+    value: Data,
+    // Dot each bit was last driven.
+    refreshed: [u64; 8],
+    now: u64,
+}
+
+impl Latch {
+    pub fn new() -> Self {
+        Latch {
+            value: 0,
+            refreshed: [0; 8],
+            now: 0,
+        }
+    }
+
+    pub fn read(&self) -> Data {
+        (0..8)
+            .filter(|&bit| self.now - self.refreshed[bit] < DECAY_DOTS)
+            .fold(0, |value, bit| value | (self.value & (1 << bit)))
+    }
+
+    pub fn write(&mut self, data: Data) {
+        self.write_bits(data, 0xFF);
+    }
+
+    // Only the bits in `mask` are driven, the others keep decaying.
+    pub fn write_bits(&mut self, data: Data, mask: Data) {
+        self.value = (self.value & !mask) | (data & mask);
+        for bit in 0..8 {
+            if mask & (1 << bit) != 0 {
+                self.refreshed[bit] = self.now;
+            }
+        }
+    }
+
+    // The PPU updates the clock once a frame, which is precise enough for
+    // a decay of several hundred milliseconds.
+    pub fn set_dot(&mut self, now: u64) {
+        self.now = now;
+    }
+}
+
+#[test]
+fn test_latch_decay() {
+    let mut latch = Latch::new();
+    latch.write(0xFF);
+    latch.set_dot(DECAY_DOTS / 2);
+    latch.write_bits(0x0F, 0x0F);
+    assert_eq!(latch.read(), 0xFF);
+    latch.set_dot(DECAY_DOTS);
+    // The high bits were not driven since the first write.
+    assert_eq!(latch.read(), 0x0F);
+    latch.set_dot(DECAY_DOTS * 3 / 2);
+    assert_eq!(latch.read(), 0x00);
+}
@@ -0,0 +1,192 @@
+use super::super::super::types::{Addr, Data};
+
+// PPU internal registers, named after loopy's "The skinny on NES scrolling".
+// $2005 and $2006 share the same latches, which is what makes mid-frame
+// scroll splits through $2006 work.
+//
+// v: current VRAM address (15 bits)
+// t: temporary VRAM address, the top left onscreen tile (15 bits)
+// x: fine X scroll (3 bits)
+// w: first or second write toggle
+//
+// v and t are laid out as
+// yyy NN YYYYY XXXXX
+// ||| || ||||| +++++-- coarse X scroll
+// ||| || +++++-------- coarse Y scroll
+// ||| ++-------------- nametable select
+// +++----------------- fine Y scroll
+#[derive(Debug)]
+pub struct Loopy {
+    v: Addr,
+    t: Addr,
+    x: Data,
+    w: bool,
+}
+
+impl Loopy {
+    pub fn new() -> Self {
+        Loopy {
+            v: 0,
+            t: 0,
+            x: 0,
+            w: false,
+        }
+    }
+
+    // The reset button clears the scroll and the write toggle, v is kept.
+    pub fn reset(&mut self) {
+        self.t = 0;
+        self.x = 0;
+        self.w = false;
+    }
+
+    pub fn get_addr(&self) -> Addr {
+        self.v & 0x3FFF
+    }
+
+    pub fn get_fine_x(&self) -> Data {
+        self.x
+    }
+
+    pub fn get_fine_y(&self) -> Addr {
+        (self.v >> 12) & 0x07
+    }
+
+    // Reading $2002 resets the write toggle.
+    pub fn reset_latch(&mut self) {
+        self.w = false;
+    }
+
+    // $2000 write: t: ...GH.. ........ <- d: ......GH
+    pub fn write_ctrl(&mut self, data: Data) {
+        self.t = (self.t & !0x0C00) | ((data as Addr & 0x03) << 10);
+    }
+
+    // $2005 first write:  t: ....... ...ABCDE <- d: ABCDE...
+    //                     x:              FGH <- d: .....FGH
+    // $2005 second write: t: FGH..AB CDE..... <- d: ABCDEFGH
+    pub fn write_scroll(&mut self, data: Data) {
+        if self.w {
+            self.t = (self.t & !0x73E0)
+                | ((data as Addr & 0x07) << 12)
+                | ((data as Addr & 0xF8) << 2);
+        } else {
+            self.t = (self.t & !0x001F) | (data as Addr >> 3);
+            self.x = data & 0x07;
+        }
+        self.w = !self.w;
+    }
+
+    // $2006 first write:  t: .CDEFGH ........ <- d: ..CDEFGH
+    //                     (bit 14 of t is cleared)
+    // $2006 second write: t: ....... ABCDEFGH <- d: ABCDEFGH
+    //                     v: <...all bits...> <- t: <...all bits...>
+    pub fn write_addr(&mut self, data: Data) {
+        if self.w {
+            self.t = (self.t & 0xFF00) | data as Addr;
+            self.v = self.t;
+        } else {
+            self.t = (self.t & 0x00FF) | ((data as Addr & 0x3F) << 8);
+        }
+        self.w = !self.w;
+    }
+
+    // $2007 access outside of rendering.
+    pub fn increment(&mut self, offset: Addr) {
+        self.v = self.v.wrapping_add(offset) & 0x7FFF;
+    }
+
+    pub fn increment_x(&mut self) {
+        if self.v & 0x001F == 31 {
+            self.v &= !0x001F;
+            self.v ^= 0x0400;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    pub fn increment_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+            return;
+        }
+        self.v &= !0x7000;
+        let mut coarse_y = (self.v & 0x03E0) >> 5;
+        if coarse_y == 29 {
+            coarse_y = 0;
+            self.v ^= 0x0800;
+        } else if coarse_y == 31 {
+            // Coarse Y set out of bounds wraps without switching nametables.
+            coarse_y = 0;
+        } else {
+            coarse_y += 1;
+        }
+        self.v = (self.v & !0x03E0) | (coarse_y << 5);
+    }
+
+    // v: ....A.. ...BCDEF <- t: ....A.. ...BCDEF
+    pub fn copy_x(&mut self) {
+        self.v = (self.v & !0x041F) | (self.t & 0x041F);
+    }
+
+    // v: GHIA.BC DEF..... <- t: GHIA.BC DEF.....
+    pub fn copy_y(&mut self) {
+        self.v = (self.v & !0x7BE0) | (self.t & 0x7BE0);
+    }
+
+    pub fn get_tile_addr(&self) -> Addr {
+        0x2000 | (self.v & 0x0FFF)
+    }
+
+    pub fn get_attribute_addr(&self) -> Addr {
+        0x23C0 | (self.v & 0x0C00) | ((self.v >> 4) & 0x38) | ((self.v >> 2) & 0x07)
+    }
+
+    // Which 2 bits of the attribute byte belong to the current tile.
+    pub fn get_attribute_shift(&self) -> Data {
+        (((self.v >> 4) & 0x04) | (self.v & 0x02)) as Data
+    }
+}
+
+#[test]
+fn set_addr() {
+    let mut reg = Loopy::new();
+    reg.write_addr(0x2a);
+    reg.write_addr(0x55);
+    assert_eq!(reg.get_addr(), 0x2a55);
+}
+
+#[test]
+fn update_addr() {
+    let mut reg = Loopy::new();
+    reg.write_addr(0x2a);
+    reg.write_addr(0x55);
+    reg.increment(32);
+    assert_eq!(reg.get_addr(), 0x2a75);
+}
+
+#[test]
+fn scroll_shares_the_addr_latch() {
+    let mut reg = Loopy::new();
+    reg.write_ctrl(0x03);
+    reg.write_scroll(0x7D);
+    reg.write_scroll(0x5E);
+    assert_eq!(reg.get_fine_x(), 0x05);
+    // Second $2006 write copies t to v.
+    reg.write_addr(0x3D);
+    reg.write_addr(0xEF);
+    assert_eq!(reg.t, 0x3DEF);
+    assert_eq!(reg.v, 0x3DEF);
+}
+
+#[test]
+fn increment_wraps_into_the_next_nametable() {
+    let mut reg = Loopy::new();
+    reg.v = 0x001F;
+    reg.increment_x();
+    assert_eq!(reg.v, 0x0400);
+    // Fine Y 7, coarse Y 29.
+    reg.v = 0x73A0;
+    reg.increment_y();
+    assert_eq!(reg.v, 0x0800);
+}
@@ -1,17 +1,14 @@
+mod latch;
+mod loopy;
 mod oam;
-mod ppu_addr;
 mod ppu_data;
-mod ppu_scroll;
 
 use super::super::types::{Addr, Data};
-use super::super::Ram;
-use super::palette::*;
-use super::PpuCtx;
 // use super::super::helper::*;
+use self::latch::Latch;
+use self::loopy::Loopy;
 use self::oam::Oam;
-use self::ppu_addr::PpuAddr;
 use self::ppu_data::PpuData;
-use self::ppu_scroll::PpuScroll;
 
 #[derive(Debug)]
 pub struct Registers {
@@ -19,9 +16,9 @@ pub struct Registers {
     pub ppu_ctrl2: Data,
     pub ppu_status: Data,
     pub oam: Oam,
-    pub ppu_addr: PpuAddr,
+    pub loopy: Loopy,
+    pub latch: Latch,
     pub ppu_data: PpuData,
-    pub ppu_scroll: PpuScroll,
 }
 
 // PPU power up state
@@ -48,9 +45,9 @@ pub struct Registers {
 */
 
 pub trait PpuRegisters {
-    fn read<P: PaletteRam>(&mut self, addr: Addr, ctx: &mut PpuCtx<P>) -> Data;
+    fn read(&mut self, addr: Addr) -> Data;
 
-    fn write<P: PaletteRam>(&mut self, addr: Addr, data: Data, ctx: &mut PpuCtx<P>);
+    fn write(&mut self, addr: Addr, data: Data);
 
     fn is_sprite_8x8(&self) -> bool;
 
@@ -58,31 +55,37 @@ pub trait PpuRegisters {
 
     fn set_vblank(&mut self);
 
+    fn is_vblank(&self) -> bool;
+
     fn set_sprite_hit(&mut self);
 
     fn clear_sprite_hit(&mut self);
 
+    fn set_sprite_overflow(&mut self);
+
+    fn clear_sprite_overflow(&mut self);
+
     fn get_sprite_table_offset(&self) -> Addr;
 
     fn get_background_table_offset(&self) -> Addr;
 
     fn get_ppu_addr_increment_value(&self) -> usize;
 
-    fn get_name_table_id(&self) -> Data;
-
-    fn get_scroll_x(&self) -> Data;
-
-    fn get_scroll_y(&self) -> Data;
-
     fn is_irq_enable(&self) -> bool;
 
     fn is_background_enable(&self) -> bool;
 
     fn is_sprite_enable(&self) -> bool;
 
+    fn is_rendering_enabled(&self) -> bool;
+
     fn is_background_masked(&self) -> bool;
 
     fn is_sprite_masked(&self) -> bool;
+
+    fn is_grayscale(&self) -> bool;
+
+    fn get_emphasis(&self) -> Data;
 }
 
 impl Registers {
@@ -92,12 +95,21 @@ impl Registers {
             ppu_ctrl2: 0,
             ppu_status: 0,
             oam: Oam::new(),
-            ppu_addr: PpuAddr::new(),
+            loopy: Loopy::new(),
+            latch: Latch::new(),
             ppu_data: PpuData::new(),
-            ppu_scroll: PpuScroll::new(),
         }
     }
 
+    // State after the reset button.
+    // PPUSTATUS, OAMADDR and PPUADDR keep their values.
+    pub fn reset(&mut self) {
+        self.ppu_ctrl1 = 0;
+        self.ppu_ctrl2 = 0;
+        self.loopy.reset();
+        self.ppu_data = PpuData::new();
+    }
+
     /*
     |  status register 0x2002
     | bit  | description                                 |
@@ -105,15 +117,14 @@ impl Registers {
     | 7    | 1: VBlank clear by reading this register    |
     | 6    | 1: sprite hit                               |
     | 5    | 0: less than 8, 1: 9 or more                |
-    | 4-0  | invalid                                     |
-    |      | bit4 VRAM write flag [0: success, 1: fail]  |
+    | 4-0  | open bus                                    |
     */
     fn read_status(&mut self) -> Data {
-        let data = self.ppu_status;
-        self.ppu_scroll.enable_x();
+        self.latch.write_bits(self.ppu_status, 0xE0);
+        let data = self.latch.read();
         self.clear_vblank();
         self.clear_sprite_hit();
-        self.ppu_addr.reser_latch();
+        self.loopy.reset_latch();
         data
     }
 
@@ -121,33 +132,13 @@ impl Registers {
         self.oam.write_addr(data);
     }
 
-    fn write_oam_data(&mut self, data: Data, sprite_ram: &mut Ram) {
-        self.oam.write_data(sprite_ram, data);
+    fn write_ctrl1(&mut self, data: Data) {
+        self.ppu_ctrl1 = data;
+        self.loopy.write_ctrl(data);
     }
 
     fn write_ppu_addr(&mut self, data: Data) {
-        self.ppu_addr.write(data);
-    }
-
-    fn read_ppu_data<P: PaletteRam>(&mut self, vram: &Ram, cram: &Ram, palette: &P) -> Data {
-        let addr = self.ppu_addr.get();
-        let data = self.ppu_data.read(vram, cram, addr, palette);
-        let v = self.get_ppu_addr_increment_value() as u16;
-        self.ppu_addr.update(v);
-        data
-    }
-
-    fn write_ppu_data<P: PaletteRam>(
-        &mut self,
-        data: Data,
-        vram: &mut Ram,
-        cram: &mut Ram,
-        palette: &mut P,
-    ) {
-        let addr = self.ppu_addr.get();
-        self.ppu_data.write(vram, cram, addr, data, palette);
-        let v = self.get_ppu_addr_increment_value() as u16;
-        self.ppu_addr.update(v);
+        self.loopy.write_addr(data);
     }
 }
 
@@ -160,6 +151,10 @@ impl PpuRegisters for Registers {
         self.ppu_status |= 0x80;
     }
 
+    fn is_vblank(&self) -> bool {
+        self.ppu_status & 0x80 == 0x80
+    }
+
     fn is_sprite_8x8(&self) -> bool {
         self.ppu_ctrl1 & 0x20 != 0x20
     }
@@ -172,6 +167,14 @@ impl PpuRegisters for Registers {
         self.ppu_status |= 0x40;
     }
 
+    fn clear_sprite_overflow(&mut self) {
+        self.ppu_status &= 0xDF;
+    }
+
+    fn set_sprite_overflow(&mut self) {
+        self.ppu_status |= 0x20;
+    }
+
     fn get_ppu_addr_increment_value(&self) -> usize {
         if self.ppu_ctrl1 & 0x04 == 0x04 {
             32
@@ -200,18 +203,6 @@ impl PpuRegisters for Registers {
         }
     }
 
-    fn get_name_table_id(&self) -> Data {
-        self.ppu_ctrl1 & 0x03
-    }
-
-    fn get_scroll_x(&self) -> Data {
-        self.ppu_scroll.get_x()
-    }
-
-    fn get_scroll_y(&self) -> Data {
-        self.ppu_scroll.get_y()
-    }
-
     fn is_background_enable(&self) -> bool {
         self.ppu_ctrl2 & 0x08 == 0x08
     }
@@ -220,6 +211,10 @@ impl PpuRegisters for Registers {
         self.ppu_ctrl2 & 0x10 == 0x10
     }
 
+    fn is_rendering_enabled(&self) -> bool {
+        self.ppu_ctrl2 & 0x18 != 0
+    }
+
     fn is_background_masked(&self) -> bool {
         self.ppu_ctrl2 & 0x02 == 0x02
     }
@@ -228,16 +223,23 @@ impl PpuRegisters for Registers {
         self.ppu_ctrl2 & 0x04 == 0x04
     }
 
-    fn read<P: PaletteRam>(&mut self, addr: Addr, ctx: &mut PpuCtx<P>) -> Data {
+    fn is_grayscale(&self) -> bool {
+        self.ppu_ctrl2 & 0x01 == 0x01
+    }
+
+    fn get_emphasis(&self) -> Data {
+        self.ppu_ctrl2 >> 5
+    }
+
+    fn read(&mut self, addr: Addr) -> Data {
         match addr {
             0x0002 => self.read_status(),
-            0x0004 => self.oam.read_data(&ctx.sprite_ram),
-            0x0007 => self.read_ppu_data(&ctx.vram, &ctx.cram, &ctx.palette),
-            _ => 0,
+            // Write only registers return the latch.
+            _ => self.latch.read(),
         }
     }
 
-    fn write<P: PaletteRam>(&mut self, addr: Addr, data: Data, ctx: &mut PpuCtx<P>) {
+    fn write(&mut self, addr: Addr, data: Data) {
         match addr {
             /*
               Control Register1 0x2000
@@ -254,15 +256,14 @@ impl PpuRegisters for Registers {
             |      |            0x02: 0x2800                     |
             |      |            0x03: 0x2C00                     |
             */
-            0x0000 => self.ppu_ctrl1 = data,
+            0x0000 => self.write_ctrl1(data),
             /*
                Control Register2 0x2001
              | bit  | description                                 |
              +------+---------------------------------------------+
-             |  7-5 | Background color  0x00: Black               |
-             |      |                   0x01: Green               |
-             |      |                   0x02: Blue                |
-             |      |                   0x04: Red                 |
+             |  7   | Emphasize blue                              |
+             |  6   | Emphasize green                             |
+             |  5   | Emphasize red                               |
              |  4   | Enable sprite                               |
              |  3   | Enable background                           |
              |  2   | Sprite mask       render left end           |
@@ -280,10 +281,8 @@ impl PpuRegisters for Registers {
             // Sprite 0 hit does not trigger in any area where the background or sprites are hidden.
             0x0001 => self.ppu_ctrl2 = data,
             0x0003 => self.write_oam_addr(data),
-            0x0004 => self.write_oam_data(data, &mut ctx.sprite_ram),
-            0x0005 => self.ppu_scroll.write(data),
+            0x0005 => self.loopy.write_scroll(data),
             0x0006 => self.write_ppu_addr(data),
-            0x0007 => self.write_ppu_data(data, &mut ctx.vram, &mut ctx.cram, &mut ctx.palette),
             _ => (),
         }
     }
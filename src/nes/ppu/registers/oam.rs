@@ -1,14 +1,24 @@
 use super::super::super::ram::*;
 use super::super::super::types::{Addr, Data};
 
+// OAM is DRAM that is only refreshed while rendering. A row of 8 bytes left
+// alone for longer than this many dots loses its contents.
+// A bit over a millisecond, real consoles vary with temperature.
+const DECAY_DOTS: u64 = 3000 * 3;
+
 #[derive(Debug)]
 pub struct Oam {
     addr: Addr,
+    // Dot of the last refresh of each row.
+    refreshed: [u64; 0x20],
 }
 
 impl Oam {
     pub fn new() -> Self {
-        Oam { addr: 0 }
+        Oam {
+            addr: 0,
+            refreshed: [0; 0x20],
+        }
     }
 
     // OAM address ($2003) > write
@@ -22,6 +32,7 @@ impl Oam {
         self.addr = 0;
     }
 
+    #[cfg(test)]
     pub fn get_addr(&self) -> Addr {
         self.addr
     }
@@ -36,14 +47,46 @@ impl Oam {
     // Access: read, write
     // Write OAM data here. Writes will increment OAMADDR after the write;
     // reads during vertical or forced blanking return the value from OAM at that address but do not increment.
+    // Bits 2-4 of the attribute byte do not exist and read back as 0.
     pub fn write_data(&mut self, ram: &mut Ram, data: Data) {
+        let data = if self.addr & 0x03 == 0x02 {
+            data & 0xE3
+        } else {
+            data
+        };
         ram.write(self.addr, data);
-        self.addr += 1;
+        self.addr = (self.addr + 1) & 0xFF;
+    }
+
+    // Writes while rendering do not reach OAM, they bump only the high 6 bits
+    // of the address, which is the sprite number.
+    pub fn write_data_while_rendering(&mut self) {
+        self.addr = (self.addr + 4) & 0xFF;
     }
 
     pub fn read_data(&self, ram: &Ram) -> Data {
         ram.read(self.addr)
     }
+
+    // Refreshes the row at the address, after it decays if it was left alone too long.
+    pub fn refresh_row(&mut self, ram: &mut Ram, now: u64) {
+        self.refresh(ram, (self.addr >> 3) as usize, now);
+    }
+
+    pub fn refresh_all(&mut self, ram: &mut Ram, now: u64) {
+        for row in 0..self.refreshed.len() {
+            self.refresh(ram, row, now);
+        }
+    }
+
+    fn refresh(&mut self, ram: &mut Ram, row: usize, now: u64) {
+        if now - self.refreshed[row] > DECAY_DOTS {
+            for i in 0..8 {
+                ram.write((row * 8 + i) as Addr, 0xFF);
+            }
+        }
+        self.refreshed[row] = now;
+    }
 }
 
 #[test]
@@ -52,3 +95,27 @@ fn set_addr() {
     reg.write_addr(0xaa);
     assert_eq!(reg.get_addr(), 0xaa);
 }
+
+#[test]
+fn attribute_unused_bits() {
+    let mut reg = Oam::new();
+    let mut ram = Ram::new(vec![0; 0x100]);
+    reg.write_addr(0x02);
+    reg.write_data(&mut ram, 0xFF);
+    reg.write_data(&mut ram, 0xFF);
+    assert_eq!(ram.read(0x02), 0xE3);
+    assert_eq!(ram.read(0x03), 0xFF);
+}
+
+#[test]
+fn decay() {
+    let mut reg = Oam::new();
+    let mut ram = Ram::new(vec![0; 0x100]);
+    reg.refresh_all(&mut ram, DECAY_DOTS);
+    assert!(ram.field.iter().all(|&v| v == 0));
+    reg.write_addr(0x08);
+    reg.refresh_row(&mut ram, DECAY_DOTS * 2 + 1);
+    assert_eq!(ram.read(0x00), 0x00);
+    assert_eq!(ram.read(0x08), 0xFF);
+    assert_eq!(ram.read(0x10), 0x00);
+}
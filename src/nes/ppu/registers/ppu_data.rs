@@ -1,6 +1,7 @@
+use super::super::super::mmc::Mmc;
 use super::super::super::types::{Addr, Data};
-use super::super::super::Ram;
 use super::super::palette::*;
+use super::super::{PpuConfig, PpuCtx};
 
 #[derive(Debug)]
 pub struct PpuData {
@@ -17,52 +18,24 @@ impl PpuData {
         PpuData { buf: 0 }
     }
 
-    pub fn read<P: PaletteRam>(&mut self, vram: &Ram, cram: &Ram, addr: Addr, palette: &P) -> Data {
-        let buf = self.buf;
-        // println!("vram cpu read0 {:X}", addr);
-        if addr >= 0x2000 {
-            let addr = self.calc_addr(addr);
-            // Reading palette data from $3F00-$3FFF works differently.
-            // The palette data is placed immediately on the data bus, and hence no dummy read is required.
-            // Reading the palettes still updates the internal buffer though, but the data placed in it is the mirrored nametable data
-            // that would appear "underneath" the palette. (Checking the PPU memory map should make this clearer.)
-            if addr >= 0x3F00 {
-                // println!("vram cpu read {:X}", addr);
-                self.buf = vram.read(addr);
-                return palette.read(addr - 0x3f00);
-            }
-            self.buf = vram.read(addr);
-        } else {
-            self.buf = cram.read(addr);
-        }
-        buf
-    }
-
-    pub fn write<P: PaletteRam>(
+    // Reads return the internal buffer, which is then filled from `addr`.
+    pub fn read<P: PaletteRam>(
         &mut self,
-        vram: &mut Ram,
-        cram: &mut Ram,
         addr: Addr,
-        data: Data,
-        palette: &mut P,
-    ) {
-        if addr >= 0x2000 {
-            if addr >= 0x3f00 && addr < 0x4000 {
-                palette.write(addr - 0x3f00, data);
-            } else {
-                let addr = self.calc_addr(addr);
-                vram.write(addr, data);
-            }
-        } else {
-            cram.write(addr, data);
-        }
-    }
-
-    fn calc_addr(&self, addr: Addr) -> Addr {
-        if addr >= 0x3000 && addr < 0x3f00 {
-            addr - 0x3000
-        } else {
-            addr - 0x2000
+        ctx: &PpuCtx<P>,
+        config: &PpuConfig,
+        mmc: &Mmc,
+    ) -> Data {
+        let buf = self.buf;
+        // Reading palette data from $3F00-$3FFF works differently.
+        // The palette data is placed immediately on the data bus, and hence no dummy read is required.
+        // Reading the palettes still updates the internal buffer though, but the data placed in it is the mirrored nametable data
+        // that would appear "underneath" the palette. (Checking the PPU memory map should make this clearer.)
+        if addr >= 0x3F00 {
+            self.buf = ctx.read(addr - 0x1000, config, mmc);
+            return ctx.read(addr, config, mmc);
         }
+        self.buf = ctx.read(addr, config, mmc);
+        buf
     }
 }
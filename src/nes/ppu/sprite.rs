@@ -1,58 +1,196 @@
-use self::super::palette::*;
-use self::super::sprite_utils::*;
+use self::super::super::types::{Addr, Data};
 use self::super::Mmc;
 use self::super::Ram;
 
-const SPRITES_NUMBER: u16 = 0x100;
+const SPRITES_NUMBER: usize = 0x40;
+const SPRITES_PER_LINE: usize = 8;
 
-pub type SpritesWithCtx = Vec<SpriteWithCtx>;
+#[derive(Debug, Clone, Copy)]
+pub struct SpritePixel {
+    // Palette address, 0 when transparent.
+    pub color: Data,
+    pub is_behind_background: bool,
+    pub is_sprite_zero: bool,
+}
+
+const TRANSPARENT: SpritePixel = SpritePixel {
+    color: 0,
+    is_behind_background: false,
+    is_sprite_zero: false,
+};
 
+// Sprite pixels of the next line, built while the current line finishes.
 #[derive(Debug)]
-pub struct SpriteWithCtx {
-    pub sprite: Sprite,
-    pub position: SpritePosition,
-    pub attr: u8,
-    pub palette: PaletteList,
+pub struct SpriteLine {
+    pixels: Vec<SpritePixel>,
+    // Copies of the OAM entries found on the line, 4 bytes each.
+    secondary_oam: Vec<Data>,
+    // Whether the first entry of secondary OAM is sprite 0.
+    has_sprite_zero: bool,
 }
 
-pub fn build_sprites<P: PaletteRam>(
-    cram: &Ram,
-    sprite_ram: &Ram,
-    palette: &P,
-    offset: u16,
-    is_8x8: bool,
-    mmc: &Mmc,
-) -> SpritesWithCtx {
-    let mut buf: SpritesWithCtx = vec![];
-    for i in 0..(SPRITES_NUMBER / 4) {
-        // INFO: Offset sprite Y position, because First and last 8line is not rendered.
-        let base = i * 4;
-        let y = sprite_ram.read(base);
-        if y >= 8 && y < 224 {
-            let sprite_id = sprite_ram.read(base + 1);
-            let attr = sprite_ram.read(base + 2);
-            let (offset, sprite_id) = if is_8x8 {
-                (offset, sprite_id)
-            } else {
-                // 76543210
-                // ||||||||
-                // |||||||+- Bank ($0000 or $1000) of tiles
-                // +++++++-- Tile number of top of sprite (0 to 254; bottom half gets the next tile)
-                let offset = 0x1000u16 * (sprite_id & 0x01) as u16;
-                let sprite_id = sprite_id & 0xFE;
-                (offset, sprite_id)
-            };
-            let x = sprite_ram.read(base + 3);
-            let sprite = build(&cram, sprite_id as u8, offset, &mmc, is_8x8);
-            let position: SpritePosition = (x, y - 8);
-            let palette_id = attr & 0x03;
-            buf.push(SpriteWithCtx {
-                sprite,
-                position,
-                attr,
-                palette: palette.get(palette_id, PaletteType::Sprite),
-            });
-        }
-    }
-    buf
+impl SpriteLine {
+    pub fn new() -> Self {
+        SpriteLine {
+            pixels: vec![TRANSPARENT; 256],
+            secondary_oam: Vec::with_capacity(SPRITES_NUMBER * 4),
+            has_sprite_zero: false,
+        }
+    }
+
+    pub fn get(&self, x: usize) -> SpritePixel {
+        self.pixels[x]
+    }
+
+    pub fn clear(&mut self) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = TRANSPARENT;
+        }
+        self.secondary_oam.clear();
+        self.has_sprite_zero = false;
+    }
+
+    // What the sprite fetch sees in secondary OAM, unused slots read $FF.
+    pub fn read_secondary_oam(&self, index: usize) -> Data {
+        if index < SPRITES_PER_LINE * 4 && index < self.secondary_oam.len() {
+            self.secondary_oam[index]
+        } else {
+            0xFF
+        }
+    }
+
+    // Copies the sprites in range of `line` to secondary OAM, and returns
+    // whether the sprite overflow flag is set.
+    // Only 8 sprites fit in secondary OAM. Without the limit the rest are kept
+    // for drawing too, but the overflow flag still behaves like the hardware.
+    pub fn evaluate(
+        &mut self,
+        line: usize,
+        sprite_ram: &Ram,
+        is_8x8: bool,
+        has_sprite_limit: bool,
+    ) -> bool {
+        self.secondary_oam.clear();
+        let height = if is_8x8 { 8 } else { 16 };
+        let is_in_range = |y: Data| line >= y as usize && line - (y as usize) < height;
+        self.has_sprite_zero = is_in_range(sprite_ram.read(0));
+        let mut n = 0;
+        while n < SPRITES_NUMBER && self.secondary_oam.len() < SPRITES_PER_LINE * 4 {
+            if is_in_range(sprite_ram.read(n as Addr * 4)) {
+                self.copy_entry(n, sprite_ram);
+            }
+            n += 1;
+        }
+        if !has_sprite_limit {
+            for n in n..SPRITES_NUMBER {
+                if is_in_range(sprite_ram.read(n as Addr * 4)) {
+                    self.copy_entry(n, sprite_ram);
+                }
+            }
+        }
+        // Once secondary OAM is full the PPU keeps looking for a 9th sprite,
+        // but it also increments the byte index within the entry on a miss,
+        // so tile, attribute and X bytes are compared as if they were Y.
+        let mut m = 0;
+        while n < SPRITES_NUMBER {
+            if is_in_range(sprite_ram.read((n * 4 + m) as Addr)) {
+                return true;
+            }
+            n += 1;
+            m = (m + 1) & 0x03;
+        }
+        false
+    }
+
+    fn copy_entry(&mut self, n: usize, sprite_ram: &Ram) {
+        for i in 0..4 {
+            let data = sprite_ram.read((n * 4 + i) as Addr);
+            self.secondary_oam.push(data);
+        }
+    }
+
+    // A sprite with Y position y is drawn on lines y + 1 to y + height,
+    // so the sprites found on `line` are the ones of the next line.
+    pub fn build(&mut self, line: usize, cram: &Ram, offset: Addr, is_8x8: bool, mmc: &Mmc) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = TRANSPARENT;
+        }
+        let height = if is_8x8 { 8 } else { 16 };
+        for (i, entry) in self.secondary_oam.chunks(4).enumerate() {
+            let y = entry[0] as usize;
+            let sprite_id = entry[1];
+            let attr = entry[2];
+            let x = entry[3] as usize;
+            let row = line - y;
+            let row = if attr & 0x80 == 0x80 { height - 1 - row } else { row };
+            let addr = pattern_addr(sprite_id, row as Addr, offset, is_8x8);
+            let low = cram.read(mmc.create_chram_addr(addr));
+            let high = cram.read(mmc.create_chram_addr(addr + 8));
+            for j in 0..8 {
+                let px = x + j;
+                if px >= 256 {
+                    break;
+                }
+                let bit = if attr & 0x40 == 0x40 { 0x01 << j } else { 0x80 >> j };
+                let color = ((low & bit != 0) as Data) | (((high & bit != 0) as Data) << 1);
+                // The first opaque sprite wins, whatever its priority.
+                if color == 0 || self.pixels[px].color != 0 {
+                    continue;
+                }
+                self.pixels[px] = SpritePixel {
+                    color: 0x10 | (attr & 0x03) << 2 | color,
+                    is_behind_background: attr & 0x20 == 0x20,
+                    is_sprite_zero: i == 0 && self.has_sprite_zero,
+                };
+            }
+        }
+    }
+}
+
+fn pattern_addr(sprite_id: Data, row: Addr, offset: Addr, is_8x8: bool) -> Addr {
+    if is_8x8 {
+        return offset + sprite_id as Addr * 16 + row;
+    }
+    // 76543210
+    // ||||||||
+    // |||||||+- Bank ($0000 or $1000) of tiles
+    // +++++++-- Tile number of top of sprite (0 to 254; bottom half gets the next tile)
+    let offset = 0x1000 * (sprite_id & 0x01) as Addr;
+    let sprite_id = (sprite_id & 0xFE) as Addr + row / 8;
+    offset + sprite_id * 16 + row % 8
+}
+
+#[test]
+fn test_pattern_addr() {
+    assert_eq!(pattern_addr(0x03, 2, 0x1000, true), 0x1032);
+    assert_eq!(pattern_addr(0x03, 9, 0x0000, false), 0x1031);
+}
+
+#[test]
+fn test_sprite_limit() {
+    let mut oam = Ram::new(vec![0xFF; 0x100]);
+    for n in 0..9 {
+        oam.write(n * 4, 0x10);
+    }
+    let mut sprites = SpriteLine::new();
+    assert!(sprites.evaluate(0x12, &oam, true, true));
+    assert_eq!(sprites.secondary_oam.len(), 8 * 4);
+    assert!(sprites.evaluate(0x12, &oam, true, false));
+    assert_eq!(sprites.secondary_oam.len(), 9 * 4);
+    assert!(!sprites.evaluate(0x20, &oam, true, true));
+}
+
+#[test]
+fn test_sprite_overflow_bug() {
+    let mut oam = Ram::new(vec![0xFF; 0x100]);
+    for n in 0..8 {
+        oam.write(n * 4, 0x10);
+    }
+    // After a miss on the 9th sprite, the Y of the 10th is never looked at.
+    oam.write(9 * 4, 0x10);
+    let mut sprites = SpriteLine::new();
+    assert!(!sprites.evaluate(0x12, &oam, true, true));
+    // Its tile byte is compared instead.
+    oam.write(9 * 4 + 1, 0x10);
+    assert!(sprites.evaluate(0x12, &oam, true, true));
 }
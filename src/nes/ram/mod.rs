@@ -3,6 +3,18 @@ pub struct Ram {
     pub field: Vec<u8>,
 }
 
+// Contents of RAM at power on.
+// Real RAM comes up in a mostly random state that differs between consoles,
+// so tests pick a pattern to stay deterministic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RamInit {
+    Zeros,
+    // Every byte is 0xFF.
+    Ones,
+    // Pseudo random bytes, the same for the same seed.
+    Random(u64),
+}
+
 impl Ram {
     pub fn new(buf: Vec<u8>) -> Ram {
         Ram { field: buf }
@@ -15,4 +27,45 @@ impl Ram {
     pub fn write(&mut self, addr: u16, data: u8) {
         self.field[addr as usize] = data;
     }
+
+    pub fn fill(&mut self, init: RamInit) {
+        match init {
+            RamInit::Zeros => self.field.iter_mut().for_each(|v| *v = 0x00),
+            RamInit::Ones => self.field.iter_mut().for_each(|v| *v = 0xFF),
+            RamInit::Random(seed) => {
+                // xorshift64*, which has to start from a non zero state.
+                let mut state = seed ^ 0x9E37_79B9_7F4A_7C15;
+                if state == 0 {
+                    state = 1;
+                }
+                for v in self.field.iter_mut() {
+                    state ^= state >> 12;
+                    state ^= state << 25;
+                    state ^= state >> 27;
+                    *v = (state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn fill_with_pattern() {
+    let mut ram = Ram::new(vec![0x55; 0x800]);
+    ram.fill(RamInit::Ones);
+    assert!(ram.field.iter().all(|&v| v == 0xFF));
+    ram.fill(RamInit::Zeros);
+    assert!(ram.field.iter().all(|&v| v == 0x00));
+}
+
+#[test]
+fn fill_random_is_seeded() {
+    let mut a = Ram::new(vec![0; 0x800]);
+    let mut b = Ram::new(vec![0; 0x800]);
+    a.fill(RamInit::Random(1));
+    b.fill(RamInit::Random(1));
+    assert_eq!(a.field, b.field);
+    b.fill(RamInit::Random(2));
+    assert_ne!(a.field, b.field);
+    assert!(a.field.iter().any(|&v| v != a.field[0]));
 }
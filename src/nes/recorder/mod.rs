@@ -0,0 +1,6 @@
+// Captures of the sound, for regression tests and for listening elsewhere.
+mod vgm;
+mod wav;
+
+pub use self::vgm::VgmLogger;
+pub use self::wav::WavWriter;
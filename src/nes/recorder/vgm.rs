@@ -0,0 +1,131 @@
+// VGM 1.71 log of the APU register writes, playable by VGM players.
+// Writes are timestamped in CPU cycles and turned into waits at the VGM
+// rate of 44100Hz, computed from the total time so rounding does not add up.
+// DMC samples are read from $C000-$FFFF, which is dumped into the log at the
+// start as the mappers supported here never switch it.
+#[derive(Debug)]
+pub struct VgmLogger {
+    cpu_clock: u64,
+    // CPU cycles since the start.
+    cycles: u64,
+    // VGM samples written as waits so far.
+    samples: u64,
+    commands: Vec<u8>,
+}
+
+const VGM_RATE: u64 = 44100;
+const HEADER_SIZE: usize = 0x100;
+const VERSION: u32 = 0x171;
+
+const WAIT: u8 = 0x61;
+const DATA_BLOCK: u8 = 0x67;
+const END: u8 = 0x66;
+const NES_APU_WRITE: u8 = 0xB4;
+const NES_APU_RAM: u8 = 0xC2;
+
+impl VgmLogger {
+    pub fn new(cpu_clock: usize, dmc_memory: &[u8]) -> Self {
+        let mut commands = vec![DATA_BLOCK, END, NES_APU_RAM];
+        commands.extend_from_slice(&(dmc_memory.len() as u32 + 2).to_le_bytes());
+        commands.extend_from_slice(&0xC000u16.to_le_bytes());
+        commands.extend_from_slice(dmc_memory);
+        VgmLogger {
+            cpu_clock: cpu_clock as u64,
+            cycles: 0,
+            samples: 0,
+            commands,
+        }
+    }
+
+    pub fn run(&mut self, cycles: u64) {
+        self.cycles += cycles;
+    }
+
+    // `addr` is the offset from $4000. Expansion audio registers go above
+    // $401F, as VGM does for the FDS, but no mapper here has any.
+    pub fn write(&mut self, addr: u8, data: u8) {
+        self.wait();
+        self.commands
+            .extend_from_slice(&[NES_APU_WRITE, addr, data]);
+    }
+
+    // Catches up with the CPU cycles that passed since the last command.
+    fn wait(&mut self) {
+        let target = self.cycles * VGM_RATE / self.cpu_clock;
+        let mut remaining = target - self.samples;
+        while remaining > 0 {
+            let wait = std::cmp::min(remaining, 0xFFFF);
+            self.commands.push(WAIT);
+            self.commands
+                .extend_from_slice(&(wait as u16).to_le_bytes());
+            remaining -= wait;
+        }
+        self.samples = target;
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.wait();
+        self.commands.push(END);
+        let mut buf = vec![0; HEADER_SIZE];
+        let mut put = |offset: usize, value: u32| {
+            buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+        put(0x04, (HEADER_SIZE + self.commands.len() - 0x04) as u32);
+        put(0x08, VERSION);
+        put(0x18, self.samples as u32);
+        // Relative to the field itself.
+        put(0x34, (HEADER_SIZE - 0x34) as u32);
+        put(0x84, self.cpu_clock as u32);
+        buf[0..4].copy_from_slice(b"Vgm ");
+        buf.extend_from_slice(&self.commands);
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(buf: &[u8], offset: usize) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&buf[offset..offset + 4]);
+        u32::from_le_bytes(bytes)
+    }
+
+    #[test]
+    fn header_and_commands() {
+        let mut vgm = VgmLogger::new(1_789_773, &[0xAA, 0x55]);
+        vgm.write(0x15, 0x01);
+        // A frame later, 735 samples.
+        vgm.run(29830);
+        vgm.write(0x00, 0xBF);
+        let buf = vgm.finish();
+        assert_eq!(&buf[0..4], b"Vgm ");
+        assert_eq!(read_u32(&buf, 0x04) as usize, buf.len() - 4);
+        assert_eq!(read_u32(&buf, 0x08), 0x171);
+        assert_eq!(read_u32(&buf, 0x18), 735);
+        assert_eq!(read_u32(&buf, 0x84), 1_789_773);
+        assert_eq!(
+            &buf[HEADER_SIZE..],
+            &[
+                0x67, 0x66, 0xC2, 4, 0, 0, 0, 0x00, 0xC0, 0xAA, 0x55, // DMC memory
+                0xB4, 0x15, 0x01, // $4015
+                0x61, 0xDF, 0x02, // 735 samples
+                0xB4, 0x00, 0xBF, // $4000
+                0x66,
+            ][..]
+        );
+    }
+
+    #[test]
+    fn waits_do_not_drift() {
+        let mut vgm = VgmLogger::new(1_789_773, &[]);
+        // A write every 100 cycles for 10 seconds.
+        for _ in 0..178_977 {
+            vgm.run(100);
+            vgm.write(0x11, 0x00);
+        }
+        let buf = vgm.finish();
+        assert_eq!(read_u32(&buf, 0x18), 441_000 - 1);
+    }
+}
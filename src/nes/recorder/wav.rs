@@ -0,0 +1,64 @@
+// Mono 16 bit PCM WAV file, kept in memory until `finish`.
+#[derive(Debug)]
+pub struct WavWriter {
+    sample_rate: u32,
+    data: Vec<u8>,
+}
+
+const HEADER_SIZE: usize = 44;
+
+impl WavWriter {
+    pub fn new(sample_rate: usize) -> Self {
+        WavWriter {
+            sample_rate: sample_rate as u32,
+            data: Vec::new(),
+        }
+    }
+
+    // Samples from `Context::take_audio_samples`, clipped to -1.0..1.0.
+    pub fn write_samples(&mut self, samples: &[f32]) {
+        for sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.data.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let data_size = self.data.len() as u32;
+        let mut buf = Vec::with_capacity(HEADER_SIZE + self.data.len());
+        buf.extend_from_slice(b"RIFF");
+        buf.extend_from_slice(&(HEADER_SIZE as u32 - 8 + data_size).to_le_bytes());
+        buf.extend_from_slice(b"WAVE");
+        buf.extend_from_slice(b"fmt ");
+        buf.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, 1 channel.
+        buf.extend_from_slice(&1u16.to_le_bytes());
+        buf.extend_from_slice(&1u16.to_le_bytes());
+        buf.extend_from_slice(&self.sample_rate.to_le_bytes());
+        // Bytes per second, bytes per frame and bits per sample.
+        buf.extend_from_slice(&(self.sample_rate * 2).to_le_bytes());
+        buf.extend_from_slice(&2u16.to_le_bytes());
+        buf.extend_from_slice(&16u16.to_le_bytes());
+        buf.extend_from_slice(b"data");
+        buf.extend_from_slice(&data_size.to_le_bytes());
+        buf.extend_from_slice(&self.data);
+        buf
+    }
+}
+
+#[test]
+fn test_wav() {
+    let mut wav = WavWriter::new(44100);
+    wav.write_samples(&[0.0, 0.5, -1.0, 2.0]);
+    let buf = wav.finish();
+    assert_eq!(buf.len(), HEADER_SIZE + 8);
+    assert_eq!(&buf[0..4], b"RIFF");
+    assert_eq!(&buf[4..8], &(36u32 + 8).to_le_bytes());
+    assert_eq!(&buf[24..28], &44100u32.to_le_bytes());
+    assert_eq!(&buf[40..44], &8u32.to_le_bytes());
+    let samples: Vec<i16> = buf[HEADER_SIZE..]
+        .chunks(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    assert_eq!(samples, vec![0, 16383, -32767, 32767]);
+}
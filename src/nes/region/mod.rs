@@ -0,0 +1,68 @@
+// TV system the console was built for.
+// PAL and Dendy (a Famiclone sold in the former USSR) run from a faster master
+// clock and have 312 lines per frame. Dendy keeps the NTSC CPU/PPU ratio and
+// moves vblank down so NTSC games run with the same timing during vblank.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Region {
+    Ntsc,
+    Pal,
+    Dendy,
+}
+
+impl Region {
+    // Master clock ticks per CPU cycle.
+    pub fn cpu_divider(self) -> usize {
+        match self {
+            Region::Ntsc => 12,
+            Region::Pal => 16,
+            Region::Dendy => 15,
+        }
+    }
+
+    // Master clock ticks per PPU dot.
+    pub fn ppu_divider(self) -> usize {
+        match self {
+            Region::Ntsc => 4,
+            Region::Pal | Region::Dendy => 5,
+        }
+    }
+
+    pub fn cpu_clock(self) -> usize {
+        match self {
+            Region::Ntsc => 1_789_773,
+            Region::Pal => 1_662_607,
+            Region::Dendy => 1_773_448,
+        }
+    }
+
+    pub fn frame_rate(self) -> f64 {
+        match self {
+            Region::Ntsc => 60.0988,
+            Region::Pal | Region::Dendy => 50.0070,
+        }
+    }
+
+    pub fn lines_per_frame(self) -> usize {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal | Region::Dendy => 312,
+        }
+    }
+
+    // Line where the vblank flag is set.
+    pub fn vblank_line(self) -> usize {
+        match self {
+            Region::Ntsc | Region::Pal => 241,
+            Region::Dendy => 291,
+        }
+    }
+
+    pub fn pre_render_line(self) -> usize {
+        self.lines_per_frame() - 1
+    }
+
+    // Only the NTSC PPU drops a dot on odd frames.
+    pub fn has_odd_frame_skip(self) -> bool {
+        self == Region::Ntsc
+    }
+}
@@ -64,3 +64,42 @@ pub static COLORS: &'static [(u8, u8, u8)] = &[
     (0x11, 0x11, 0x11),
     (0x11, 0x11, 0x11),
 ];
+
+// Each emphasis bit darkens the other two channels by about 18%:
+// bit 0 emphasizes red, bit 1 green and bit 2 blue.
+const EMPHASIS_ATTENUATION: f32 = 0.816_328;
+
+// The 64 colours for each of the 8 combinations of the $2001 emphasis bits,
+// indexed by emphasis << 6 | colour.
+pub fn build_palette() -> Vec<(u8, u8, u8)> {
+    let attenuate = |c: u8, is_attenuated: bool| {
+        if is_attenuated {
+            (c as f32 * EMPHASIS_ATTENUATION) as u8
+        } else {
+            c
+        }
+    };
+    (0..8)
+        .flat_map(|emphasis: u8| {
+            COLORS.iter().map(move |&(r, g, b)| {
+                let (red, green, blue) = (emphasis & 1, emphasis & 2, emphasis & 4);
+                (
+                    attenuate(r, green != 0 || blue != 0),
+                    attenuate(g, red != 0 || blue != 0),
+                    attenuate(b, red != 0 || green != 0),
+                )
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_build_palette() {
+    let palette = build_palette();
+    assert_eq!(palette.len(), 512);
+    assert_eq!(palette[0x20], COLORS[0x20]);
+    // Red emphasis keeps red and darkens green and blue.
+    assert_eq!(palette[0x40 | 0x30], (0xFF, 0xD0, 0xD0));
+    // All bits darken everything.
+    assert_eq!(palette[0x1C0 | 0x30], (0xD0, 0xD0, 0xD0));
+}
@@ -3,6 +3,7 @@ extern crate regex;
 extern crate rustynes;
 extern crate sdl2;
 
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
@@ -13,12 +14,76 @@ use sdl2::Sdl;
 use std::time::{Duration, SystemTime};
 
 use rustynes::nes;
-use rustynes::nes::Context;
+use rustynes::nes::{Context, RamInit, Region, WavWriter};
 use std::env;
 use std::fs;
 
 const WIDTH: u32 = 256;
-const HEIGHT: u32 = 224;
+const HEIGHT: u32 = 240;
+
+// Audio queued ahead of the device, in seconds. Lower is less latency,
+// but underruns when a frame takes too long.
+const AUDIO_LATENCY: f64 = 0.05;
+// The most the sample rate is bent to keep the queue at AUDIO_LATENCY.
+// Half a percent of pitch is not audible.
+const MAX_RATE_ADJUSTMENT: f64 = 0.005;
+
+// Lines and columns hidden by a TV, cropped from the picture.
+#[derive(Clone, Copy)]
+pub struct Overscan {
+    top: u32,
+    bottom: u32,
+    left: u32,
+    right: u32,
+}
+
+impl Overscan {
+    // "top,bottom,left,right", e.g. "8,8,0,0".
+    fn parse(s: &str) -> Option<Overscan> {
+        let v: Vec<u32> = s
+            .split(',')
+            .map(|n| n.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        if v.len() != 4 {
+            return None;
+        }
+        if v[0].checked_add(v[1])? >= HEIGHT || v[2].checked_add(v[3])? >= WIDTH {
+            return None;
+        }
+        Some(Overscan {
+            top: v[0],
+            bottom: v[1],
+            left: v[2],
+            right: v[3],
+        })
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH - self.left - self.right
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT - self.top - self.bottom
+    }
+}
+
+const DEFAULT_OVERSCAN: Overscan = Overscan {
+    top: 8,
+    bottom: 8,
+    left: 0,
+    right: 0,
+};
+
+// "auto" keeps the region from the ROM header.
+fn parse_region(s: &str) -> Option<Option<Region>> {
+    match s {
+        "auto" => Some(None),
+        "ntsc" => Some(Some(Region::Ntsc)),
+        "pal" => Some(Some(Region::Pal)),
+        "dendy" => Some(Some(Region::Dendy)),
+        _ => None,
+    }
+}
 
 const PAD_A: u8 = 0x01;
 const PAD_B: u8 = 0x02;
@@ -29,29 +94,44 @@ const PAD_D: u8 = 0x20;
 const PAD_L: u8 = 0x40;
 const PAD_R: u8 = 0x80;
 
-fn keycode_to_pad(key: Keycode) -> u8 {
+// The controller the key belongs to, and its button.
+fn keycode_to_pad(key: Keycode) -> (usize, u8) {
     match key {
-        Keycode::X => PAD_A,
-        Keycode::Z => PAD_B,
-        Keycode::A => PAD_SELECT,
-        Keycode::S => PAD_START,
-        Keycode::Up => PAD_U,
-        Keycode::Down => PAD_D,
-        Keycode::Left => PAD_L,
-        Keycode::Right => PAD_R,
-        _ => 0,
+        Keycode::X => (0, PAD_A),
+        Keycode::Z => (0, PAD_B),
+        Keycode::A => (0, PAD_SELECT),
+        Keycode::S => (0, PAD_START),
+        Keycode::Up => (0, PAD_U),
+        Keycode::Down => (0, PAD_D),
+        Keycode::Left => (0, PAD_L),
+        Keycode::Right => (0, PAD_R),
+        Keycode::M => (1, PAD_A),
+        Keycode::N => (1, PAD_B),
+        Keycode::Y => (1, PAD_SELECT),
+        Keycode::U => (1, PAD_START),
+        Keycode::I => (1, PAD_U),
+        Keycode::K => (1, PAD_D),
+        Keycode::J => (1, PAD_L),
+        Keycode::L => (1, PAD_R),
+        _ => (0, 0),
     }
 }
 
 pub struct App {
     sdl_context: Sdl,
     canvas: WindowCanvas,
+    overscan: Overscan,
+    audio: AudioQueue<f32>,
+    audio_buf: Vec<f32>,
+    // Files written when the window is closed.
+    wav: Option<(String, WavWriter)>,
+    vgm_path: Option<String>,
 
     ctx: Option<Context>,
 }
 
 impl App {
-    pub fn new() -> App {
+    pub fn new(overscan: Overscan) -> App {
         let scale = 3.0;
 
         let sdl_context = sdl2::init().unwrap();
@@ -59,8 +139,8 @@ impl App {
         let window = video_subsystem
             .window(
                 "rustynes",
-                (WIDTH as f32 * scale) as u32,
-                (HEIGHT as f32 * scale) as u32,
+                (overscan.width() as f32 * scale) as u32,
+                (overscan.height() as f32 * scale) as u32,
             )
             .position_centered()
             .build()
@@ -68,23 +148,77 @@ impl App {
         let mut canvas = window.into_canvas().build().unwrap();
         canvas.set_scale(scale, scale).unwrap();
 
+        let audio_subsystem = sdl_context.audio().unwrap();
+        let desired = AudioSpecDesired {
+            freq: Some(44100),
+            channels: Some(1),
+            samples: Some(512),
+        };
+        let audio = audio_subsystem.open_queue(None, &desired).unwrap();
+        let audio_buf = vec![0.0; audio.spec().freq as usize];
+
         App {
             sdl_context,
             canvas,
+            overscan,
+            audio,
+            audio_buf,
+            wav: None,
+            vgm_path: None,
             ctx: None,
         }
     }
 
-    pub fn set_rom(&mut self, mut rom: Vec<u8>) {
+    pub fn set_rom(&mut self, mut rom: Vec<u8>, region: Option<Region>) {
         let mut ctx = Context::new(&mut rom);
-        nes::reset(&mut ctx);
+        match region {
+            Some(region) => ctx.set_region(region, RamInit::Zeros),
+            None => nes::reset(&mut ctx),
+        }
+        ctx.set_sample_rate(self.audio.spec().freq as usize);
         self.ctx = Some(ctx);
     }
 
+    pub fn record_wav(&mut self, path: String) {
+        let wav = WavWriter::new(self.audio.spec().freq as usize);
+        self.wav = Some((path, wav));
+    }
+
+    // Call after `set_rom`.
+    pub fn record_vgm(&mut self, path: String) {
+        if let Some(ctx) = &mut self.ctx {
+            ctx.start_vgm_log();
+            self.vgm_path = Some(path);
+        }
+    }
+
+    fn save_recordings(&mut self) {
+        if let Some((path, wav)) = self.wav.take() {
+            if let Err(err) = fs::write(&path, wav.finish()) {
+                eprintln!("Cannot write {}: {}", path, err);
+            }
+        }
+        let vgm = match &mut self.ctx {
+            Some(ctx) => ctx.finish_vgm_log(),
+            None => None,
+        };
+        if let (Some(path), Some(vgm)) = (self.vgm_path.take(), vgm) {
+            if let Err(err) = fs::write(&path, vgm) {
+                eprintln!("Cannot write {}: {}", path, err);
+            }
+        }
+    }
+
     pub fn run(&mut self) {
         let mut event_pump = self.sdl_context.event_pump().unwrap();
-        let mut pad = 0;
+        let mut pads = [0; 2];
         let mut prev_time = SystemTime::now();
+        let frame_rate = match &self.ctx {
+            Some(ctx) => ctx.get_region().frame_rate(),
+            None => Region::Ntsc.frame_rate(),
+        };
+        let frame_time = (1_000_000_000f64 / frame_rate) as u32;
+        self.start_audio();
         'running: loop {
             for event in event_pump.poll_iter() {
                 match event {
@@ -96,18 +230,21 @@ impl App {
                     Event::KeyDown {
                         keycode: Some(key), ..
                     } => {
-                        pad |= keycode_to_pad(key);
+                        let (player, button) = keycode_to_pad(key);
+                        pads[player] |= button;
                     }
                     Event::KeyUp {
                         keycode: Some(key), ..
                     } => {
-                        pad &= !keycode_to_pad(key);
+                        let (player, button) = keycode_to_pad(key);
+                        pads[player] &= !button;
                     }
                     _ => {}
                 }
             }
 
-            self.update(pad);
+            self.update(pads);
+            self.play_audio();
             self.render();
             self.canvas.present();
 
@@ -115,8 +252,8 @@ impl App {
                 .duration_since(prev_time)
                 .expect("Time went backwards")
                 .as_nanos();
-            let wait = if elapsed_time < 1_000_000_000u128 / 60 {
-                1_000_000_000u32 / 60 - (elapsed_time as u32)
+            let wait = if elapsed_time < frame_time as u128 {
+                frame_time - (elapsed_time as u32)
             } else {
                 0
             };
@@ -124,25 +261,71 @@ impl App {
 
             prev_time = SystemTime::now();
         }
+        self.save_recordings();
     }
 
-    fn update(&mut self, pad: u8) {
+    fn update(&mut self, pads: [u8; 2]) {
         let optctx = &mut self.ctx;
         match optctx {
             Some(ctx) => {
-                nes::run(ctx, pad);
+                nes::run(ctx, pads[0], pads[1]);
             }
             None => (),
         }
     }
 
+    // Samples the device holds at the target latency.
+    fn audio_target(&self) -> usize {
+        (self.audio.spec().freq as f64 * AUDIO_LATENCY) as usize
+    }
+
+    fn queued_samples(&self) -> usize {
+        self.audio.size() as usize / std::mem::size_of::<f32>()
+    }
+
+    // Starts with the queue at the target, filled with silence.
+    fn start_audio(&mut self) {
+        self.audio.clear();
+        self.audio.queue(&vec![0.0; self.audio_target()]);
+        self.audio.resume();
+    }
+
+    // Frames are paced by the video timer, which drifts from the audio clock.
+    // Dynamic rate control bends the sample rate a little so the queue stays
+    // around the target instead of running dry or lagging more and more.
+    fn play_audio(&mut self) {
+        let len = match &mut self.ctx {
+            Some(ctx) => ctx.take_audio_samples(&mut self.audio_buf),
+            None => return,
+        };
+        self.audio.queue(&self.audio_buf[..len]);
+        if let Some((_, wav)) = &mut self.wav {
+            wav.write_samples(&self.audio_buf[..len]);
+        }
+
+        // Way off after a stall, e.g. while the window was dragged.
+        if self.queued_samples() > self.audio_target() * 4 {
+            self.start_audio();
+        }
+        let target = self.audio_target();
+        let queued = self.queued_samples();
+        let fill = (queued as f64 - target as f64) / target as f64;
+        let ratio = 1.0 - fill.clamp(-1.0, 1.0) * MAX_RATE_ADJUSTMENT;
+        if let Some(ctx) = &mut self.ctx {
+            ctx.set_sample_rate_ratio(ratio);
+        }
+    }
+
     fn render(&mut self) {
         match &mut self.ctx {
             Some(ctx) => {
                 let buf = nes::get_render_buf(ctx);
-                for i in 0..HEIGHT {
-                    for j in 0..WIDTH {
-                        let base = ((i * WIDTH + j) * 4) as usize;
+                let overscan = self.overscan;
+                for i in 0..overscan.height() {
+                    for j in 0..overscan.width() {
+                        let y = i + overscan.top;
+                        let x = j + overscan.left;
+                        let base = ((y * WIDTH + x) * 4) as usize;
                         let r = buf[base + 0];
                         let g = buf[base + 1];
                         let b = buf[base + 2];
@@ -161,32 +344,6 @@ fn canvas_render(_ptr: *const u8, _len: usize) {
     //println!("canvas_render, len={}", len);
 }
 
-#[no_mangle]
-fn start_oscillator(_index: usize) {}
-#[no_mangle]
-fn stop_oscillator(_index: usize) {}
-//#[no_mangle]
-// fn close_oscillator(index: usize) {}
-#[no_mangle]
-fn set_oscillator_frequency(_index: usize, _freq: usize) {}
-#[no_mangle]
-fn change_oscillator_frequency(_index: usize, _freq: usize) {}
-#[no_mangle]
-fn set_oscillator_volume(_index: usize, _volume: f32) {}
-#[no_mangle]
-fn set_oscillator_pulse_width(_index: usize, _width: f32) {}
-
-#[no_mangle]
-fn set_noise_frequency(_freq: f32) {}
-#[no_mangle]
-fn set_noise_volume(_volume: f32) {}
-#[no_mangle]
-fn stop_noise() {}
-#[no_mangle]
-fn start_noise() {}
-//#[no_mangle]
-//fn close_noise();
-
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
@@ -194,12 +351,40 @@ fn main() {
         std::process::exit(1);
     }
 
-    let mut app = App::new();
+    let mut overscan = DEFAULT_OVERSCAN;
+    let mut region = None;
+    let mut wav_path = None;
+    let mut vgm_path = None;
+    for arg in &args[2..] {
+        if let Some(Some(o)) = arg.strip_prefix("--overscan=").map(Overscan::parse) {
+            overscan = o;
+        } else if let Some(Some(r)) = arg.strip_prefix("--region=").map(parse_region) {
+            region = r;
+        } else if let Some(path) = arg.strip_prefix("--record-wav=") {
+            wav_path = Some(path.to_string());
+        } else if let Some(path) = arg.strip_prefix("--record-vgm=") {
+            vgm_path = Some(path.to_string());
+        } else {
+            eprintln!(
+                "unknown option {}, usage: <.nes file> [--overscan=top,bottom,left,right] [--region=auto|ntsc|pal|dendy] [--record-wav=out.wav] [--record-vgm=out.vgm]",
+                arg
+            );
+            std::process::exit(1);
+        }
+    }
+
+    let mut app = App::new(overscan);
 
     let filename = &args[1];
     match fs::read(filename) {
         Result::Ok(rom) => {
-            app.set_rom(rom);
+            app.set_rom(rom, region);
+            if let Some(path) = wav_path {
+                app.record_wav(path);
+            }
+            if let Some(path) = vgm_path {
+                app.record_vgm(path);
+            }
             app.run();
         }
         Result::Err(err) => {